@@ -1,91 +1,97 @@
-use poc::wit::types::*;
+// Generated exports flatten every record field into a parameter
+#![allow(clippy::too_many_arguments)]
+
+use common::Capture;
+use common::types::*;
 
 wit_bindgen::generate!({
-    world: "universe",
+    world: "authorizer",
     path: "../../wit",
+    with: {
+        "poc:wit/types": common::types,
+    },
 });
 
 struct Authorizer;
 
 /// Default implementation for permission validation.
 impl Guest for Authorizer {
-    fn read_request(_args: String) -> ReadSet {
-        unimplemented!("boilerplate");
-    }
+    fn read_approval(
+        _context: Context,
+        _authority: AccountK,
+        signals: ReadSet,
+        receptors: AllowSet,
+    ) -> ReadVerdict {
+        let mut approved = vec![];
+        let rejected = signals
+            .inner
+            .into_iter()
+            .filter_map(|signal| {
+                let required = 0b0000_0001;
+                let granted = granted(&receptors, &signal.key);
+                if required & !granted == 0 {
+                    approved.push(signal);
+                    return None;
+                }
 
-    fn read_approval(signals: ReadSet, receptors: AllowSet) -> bool {
-        let mut signals = signals;
-        signals.inner.retain(|signal| {
-            let approved = receptors.inner.iter().any(|receptor| {
-                let captures = receptor.key.captures(&signal.key);
-                let NodeValueAllow::AccountAsset(AccountAssetA { bit_mask }) = receptor.value;
-                let passes = 0b0000_0001 & !bit_mask == 0;
-                captures && passes
-            });
+                // Approve only the parts of the signal that some receptor allows
+                let parts = receptors
+                    .inner
+                    .iter()
+                    .filter(|receptor| required & !receptor.value.bit_mask() == 0)
+                    .filter_map(|receptor| receptor.key.intersect(&signal.key))
+                    .map(|key| ReadEntry {
+                        key,
+                        value: signal.value,
+                    });
+                approved.extend(parts);
 
-            !approved
-        });
+                Some(ReadRejection {
+                    entry: signal,
+                    required,
+                    granted,
+                })
+            })
+            .collect();
 
-        signals.inner.is_empty()
+        ReadVerdict { approved, rejected }
     }
 
-    fn write_request(_view: ViewSet, _args: String) -> WriteSet {
-        unimplemented!("boilerplate");
-    }
+    fn write_approval(
+        _context: Context,
+        _authority: AccountK,
+        signals: EventSet,
+        receptors: AllowSet,
+    ) -> WriteVerdict {
+        let rejected = signals
+            .inner
+            .into_iter()
+            .filter_map(|signal| {
+                let required = signal.value.status_bit();
+                let granted = granted(&receptors, &signal.key);
 
-    fn write_approval(signals: EventSet, receptors: AllowSet) -> bool {
-        let mut signals = signals;
-        signals.inner.retain(|signal| {
-            let NodeValueEvent::AccountAsset(AccountAssetE { status_bit }) = signal.value;
-            let receptors = receptors
-                .inner
-                .iter()
-                .filter(|receptor| receptor.key.captures(&signal.key));
-            let Some(bit_mask_union) = receptors
-                .map(|entry| {
-                    let NodeValueAllow::AccountAsset(AccountAssetA { bit_mask }) = entry.value;
-                    bit_mask
+                (required & !granted != 0).then_some(WriteRejection {
+                    entry: signal,
+                    required,
+                    granted,
                 })
-                .reduce(|acc, bit| acc | bit)
-            else {
-                return false;
-            };
+            })
+            .collect();
 
-            let approved = status_bit & !bit_mask_union == 0;
-            !approved
-        });
-
-        signals.inner.is_empty()
+        WriteVerdict { rejected }
     }
 }
 
-export!(Authorizer);
-
-// TODO: move common types to separate crate from host
-pub trait Capture<T> {
-    fn captures(&self, candidate: &T) -> bool;
+/// Unites the bit masks of every receptor capturing the signal; nothing allowed if none does.
+fn granted<T>(receptors: &AllowSet, key: &T) -> u8
+where
+    FuzzyNodeKey: Capture<T>,
+{
+    receptors
+        .inner
+        .iter()
+        .filter(|receptor| receptor.key.captures(key))
+        .fold(0, |acc, receptor| acc | receptor.value.bit_mask())
 }
 
-impl Capture<NodeKey> for FuzzyNodeKey {
-    fn captures(&self, key: &NodeKey) -> bool {
-        let (
-            FuzzyNodeKey::AccountAsset(FuzzyCompositeKey { e0: z0, e1: z1 }),
-            NodeKey::AccountAsset(CompositeKey { e0, e1 }),
-        ) = (self, key);
-        z0.as_ref().is_none_or(|z0| z0 == e0) && z1.as_ref().is_none_or(|z1| z1 == e1)
-    }
-}
-
-impl Capture<FuzzyNodeKey> for FuzzyNodeKey {
-    fn captures(&self, candidate: &FuzzyNodeKey) -> bool {
-        let (
-            FuzzyNodeKey::AccountAsset(FuzzyCompositeKey { e0: z0, e1: z1 }),
-            FuzzyNodeKey::AccountAsset(FuzzyCompositeKey { e0, e1 }),
-        ) = (self, candidate);
-        z0.as_ref()
-            .is_none_or(|z0| e0.as_ref().is_some_and(|e0| z0 == e0))
-            && z1
-                .as_ref()
-                .is_none_or(|z1| e1.as_ref().is_some_and(|e1| z1 == e1))
-    }
-}
+export!(Authorizer);
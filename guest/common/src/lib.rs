@@ -0,0 +1,216 @@
+//! WIT types shared by every guest, along with helpers on them.
+
+wit_bindgen::generate!({
+    inline: "
+        package poc:common;
+
+        world common {
+            import poc:wit/types;
+        }
+    ",
+    path: "../../wit",
+    with: {
+        "poc:wit/types": generate,
+    },
+});
+
+pub use poc::wit::types;
+
+use types::*;
+
+impl Numeric {
+    pub fn new(mantissa: u128, scale: u8) -> Self {
+        Self {
+            hi: (mantissa >> 64) as u64,
+            lo: mantissa as u64,
+            scale,
+        }
+    }
+
+    pub fn mantissa(&self) -> u128 {
+        u128::from(self.hi) << 64 | u128::from(self.lo)
+    }
+
+    /// Compares the values regardless of their scales.
+    pub fn compare(&self, other: &Self) -> std::cmp::Ordering {
+        // A mantissa that overflows on alignment exceeds any aligned one
+        let align = |numeric: &Self, scale: u8| {
+            10u128
+                .checked_pow(u32::from(scale - numeric.scale))
+                .and_then(|factor| numeric.mantissa().checked_mul(factor))
+        };
+        let scale = self.scale.max(other.scale);
+        match (align(self, scale), align(other, scale)) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (None, _) => std::cmp::Ordering::Greater,
+            (_, None) => std::cmp::Ordering::Less,
+        }
+    }
+}
+
+/// Whether a fuzzy key of a receptor captures the key of a signal.
+pub trait Capture<T> {
+    fn captures(&self, candidate: &T) -> bool;
+}
+
+impl Capture<NodeKey> for FuzzyNodeKey {
+    fn captures(&self, key: &NodeKey) -> bool {
+        match (self, key) {
+            (
+                FuzzyNodeKey::Account(FuzzySingleKey { e0: z0 }),
+                NodeKey::Account(SingleKey { e0 }),
+            )
+            | (
+                FuzzyNodeKey::AssetDefinition(FuzzySingleKey { e0: z0 }),
+                NodeKey::AssetDefinition(SingleKey { e0 }),
+            )
+            | (
+                FuzzyNodeKey::Permission(FuzzySingleKey { e0: z0 }),
+                NodeKey::Permission(SingleKey { e0 }),
+            )
+            | (
+                FuzzyNodeKey::Executable(FuzzySingleKey { e0: z0 }),
+                NodeKey::Executable(SingleKey { e0 }),
+            ) => z0.as_ref().is_none_or(|z0| z0 == e0),
+            (
+                FuzzyNodeKey::AccountAsset(FuzzyCompositeKey { e0: z0, e1: z1 }),
+                NodeKey::AccountAsset(CompositeKey { e0, e1 }),
+            )
+            | (
+                FuzzyNodeKey::AccountPermission(FuzzyCompositeKey { e0: z0, e1: z1 }),
+                NodeKey::AccountPermission(CompositeKey { e0, e1 }),
+            ) => z0.as_ref().is_none_or(|z0| z0 == e0) && z1.as_ref().is_none_or(|z1| z1 == e1),
+            _ => false,
+        }
+    }
+}
+
+/// The host resolves `This` in signals beforehand, so only `That` is ever captured by a specific
+/// receptor.
+impl Capture<FlexFuzzyNodeKey> for FuzzyNodeKey {
+    fn captures(&self, candidate: &FlexFuzzyNodeKey) -> bool {
+        let captures_flex = |z0: &Option<String>, e0: &Option<FlexKeyElem>| {
+            z0.as_ref().is_none_or(|z0| {
+                e0.as_ref()
+                    .is_some_and(|e0| matches!(e0, FlexKeyElem::That(e0) if z0 == e0))
+            })
+        };
+        match (self, candidate) {
+            (
+                FuzzyNodeKey::Account(FuzzySingleKey { e0: z0 }),
+                FlexFuzzyNodeKey::Account(FlexFuzzySingleKey { e0 }),
+            ) => captures_flex(z0, e0),
+            (
+                FuzzyNodeKey::AssetDefinition(FuzzySingleKey { e0: z0 }),
+                FlexFuzzyNodeKey::AssetDefinition(FuzzySingleKey { e0 }),
+            )
+            | (
+                FuzzyNodeKey::Permission(FuzzySingleKey { e0: z0 }),
+                FlexFuzzyNodeKey::Permission(FuzzySingleKey { e0 }),
+            )
+            | (
+                FuzzyNodeKey::Executable(FuzzySingleKey { e0: z0 }),
+                FlexFuzzyNodeKey::Executable(FuzzySingleKey { e0 }),
+            ) => z0
+                .as_ref()
+                .is_none_or(|z0| e0.as_ref().is_some_and(|e0| z0 == e0)),
+            (
+                FuzzyNodeKey::AccountAsset(FuzzyCompositeKey { e0: z0, e1: z1 }),
+                FlexFuzzyNodeKey::AccountAsset(FlexFuzzyCompositeKey { e0, e1 }),
+            )
+            | (
+                FuzzyNodeKey::AccountPermission(FuzzyCompositeKey { e0: z0, e1: z1 }),
+                FlexFuzzyNodeKey::AccountPermission(FlexFuzzyCompositeKey { e0, e1 }),
+            ) => {
+                captures_flex(z0, e0)
+                    && z1
+                        .as_ref()
+                        .is_none_or(|z1| e1.as_ref().is_some_and(|e1| z1 == e1))
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FuzzyNodeKey {
+    /// Narrows the candidate to the keys both capture, if any.
+    pub fn intersect(&self, candidate: &FlexFuzzyNodeKey) -> Option<FlexFuzzyNodeKey> {
+        // The outer `None` means nothing is captured by both
+        let intersect = |z0: &Option<String>, e0: &Option<String>| match (z0, e0) {
+            (None, e0) => Some(e0.clone()),
+            (z0, None) => Some(z0.clone()),
+            (Some(z0), Some(e0)) => (z0 == e0).then(|| Some(e0.clone())),
+        };
+        // The host resolves `This` in signals beforehand
+        let intersect_flex = |z0: &Option<String>, e0: &Option<FlexKeyElem>| {
+            let e0 = match e0 {
+                None => None,
+                Some(FlexKeyElem::That(e0)) => Some(e0.clone()),
+                Some(FlexKeyElem::This) => return None,
+            };
+            intersect(z0, &e0).map(|e0| e0.map(FlexKeyElem::That))
+        };
+        match (self, candidate) {
+            (
+                FuzzyNodeKey::Account(FuzzySingleKey { e0: z0 }),
+                FlexFuzzyNodeKey::Account(FlexFuzzySingleKey { e0 }),
+            ) => intersect_flex(z0, e0)
+                .map(|e0| FlexFuzzyNodeKey::Account(FlexFuzzySingleKey { e0 })),
+            (
+                FuzzyNodeKey::AssetDefinition(FuzzySingleKey { e0: z0 }),
+                FlexFuzzyNodeKey::AssetDefinition(FuzzySingleKey { e0 }),
+            ) => {
+                intersect(z0, e0).map(|e0| FlexFuzzyNodeKey::AssetDefinition(FuzzySingleKey { e0 }))
+            }
+            (
+                FuzzyNodeKey::Permission(FuzzySingleKey { e0: z0 }),
+                FlexFuzzyNodeKey::Permission(FuzzySingleKey { e0 }),
+            ) => intersect(z0, e0).map(|e0| FlexFuzzyNodeKey::Permission(FuzzySingleKey { e0 })),
+            (
+                FuzzyNodeKey::Executable(FuzzySingleKey { e0: z0 }),
+                FlexFuzzyNodeKey::Executable(FuzzySingleKey { e0 }),
+            ) => intersect(z0, e0).map(|e0| FlexFuzzyNodeKey::Executable(FuzzySingleKey { e0 })),
+            (
+                FuzzyNodeKey::AccountAsset(FuzzyCompositeKey { e0: z0, e1: z1 }),
+                FlexFuzzyNodeKey::AccountAsset(FlexFuzzyCompositeKey { e0, e1 }),
+            ) => Some(FlexFuzzyNodeKey::AccountAsset(FlexFuzzyCompositeKey {
+                e0: intersect_flex(z0, e0)?,
+                e1: intersect(z1, e1)?,
+            })),
+            (
+                FuzzyNodeKey::AccountPermission(FuzzyCompositeKey { e0: z0, e1: z1 }),
+                FlexFuzzyNodeKey::AccountPermission(FlexFuzzyCompositeKey { e0, e1 }),
+            ) => Some(FlexFuzzyNodeKey::AccountPermission(FlexFuzzyCompositeKey {
+                e0: intersect_flex(z0, e0)?,
+                e1: intersect(z1, e1)?,
+            })),
+            _ => None,
+        }
+    }
+}
+
+impl NodeValueAllow {
+    pub fn bit_mask(&self) -> u8 {
+        match self {
+            NodeValueAllow::Account(AccountA { bit_mask })
+            | NodeValueAllow::AssetDefinition(AssetDefinitionA { bit_mask })
+            | NodeValueAllow::AccountAsset(AccountAssetA { bit_mask })
+            | NodeValueAllow::Permission(PermissionA { bit_mask })
+            | NodeValueAllow::AccountPermission(AccountPermissionA { bit_mask })
+            | NodeValueAllow::Executable(ExecutableA { bit_mask }) => *bit_mask,
+        }
+    }
+}
+
+impl NodeValueEvent {
+    pub fn status_bit(&self) -> u8 {
+        match self {
+            NodeValueEvent::Account(AccountE { status_bit })
+            | NodeValueEvent::AssetDefinition(AssetDefinitionE { status_bit })
+            | NodeValueEvent::AccountAsset(AccountAssetE { status_bit })
+            | NodeValueEvent::Permission(PermissionE { status_bit })
+            | NodeValueEvent::AccountPermission(AccountPermissionE { status_bit })
+            | NodeValueEvent::Executable(ExecutableE { status_bit }) => *status_bit,
+        }
+    }
+}
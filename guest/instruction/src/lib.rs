@@ -1,9 +1,15 @@
-use poc::wit::types::*;
+// Generated exports flatten every record field into a parameter
+#![allow(clippy::too_many_arguments)]
+
+use common::types::*;
 use serde::Deserialize;
 
 wit_bindgen::generate!({
-    world: "universe",
+    world: "instruction",
     path: "../../wit",
+    with: {
+        "poc:wit/types": common::types,
+    },
 });
 
 struct SupplyAll;
@@ -13,19 +19,22 @@ struct Args {
     // Name of the asset to supply
     asset: String,
     // Account balances below this threshold will be supplied
-    threshold: u32,
+    threshold: u128,
     // Amount to supply to accounts
-    supply_amount: u32,
+    supply_amount: u128,
+    // Decimal scale of the threshold and the amount; whole units if omitted
+    #[serde(default)]
+    scale: u8,
     // The supplier account
     supplier: String,
 }
 
 impl Guest for SupplyAll {
-    fn read_request(args: String) -> ReadSet {
+    fn read_request(_context: Context, _authority: AccountK, args: String) -> ReadSet {
         let args: Args = serde_json::from_str(&args).expect("wrong args");
 
         let inner = vec![ReadEntry {
-            key: FuzzyNodeKey::AccountAsset(FuzzyCompositeKey {
+            key: FlexFuzzyNodeKey::AccountAsset(FlexFuzzyCompositeKey {
                 e0: None,
                 e1: Some(args.asset.to_string()),
             }),
@@ -35,34 +44,44 @@ impl Guest for SupplyAll {
         ReadSet { inner }
     }
 
-    fn read_approval(_signals: ReadSet, _receptors: AllowSet) -> bool {
-        unimplemented!("boilerplate");
-    }
-
-    fn write_request(view: ViewSet, args: String) -> WriteSet {
+    fn write_request(
+        _context: Context,
+        _authority: AccountK,
+        view: ViewSet,
+        args: String,
+    ) -> WriteSet {
         let args: Args = serde_json::from_str(&args).expect("wrong args");
+        let threshold = Numeric::new(args.threshold, args.scale);
+        let supply_amount = Numeric::new(args.supply_amount, args.scale);
 
         let inner = view
             .inner
             .into_iter()
             .filter_map(|entry| {
-                let NodeValueView::AccountAsset(value) = entry.value;
-                (value.balance < args.threshold).then(|| {
+                let (
+                    NodeKey::AccountAsset(CompositeKey { e0, e1 }),
+                    NodeValueView::AccountAsset(value),
+                ) = (entry.key, entry.value)
+                else {
+                    return None;
+                };
+                value.balance.compare(&threshold).is_lt().then(|| {
                     vec![
                         WriteEntry {
-                            key: entry.key,
+                            key: FlexNodeKey::AccountAsset(FlexCompositeKey {
+                                e0: FlexKeyElem::That(e0),
+                                e1,
+                            }),
                             value: NodeValueWrite::AccountAsset(AccountAssetW::Receive(
-                                args.supply_amount,
+                                supply_amount,
                             )),
                         },
                         WriteEntry {
-                            key: NodeKey::AccountAsset(CompositeKey {
-                                e0: args.supplier.clone(),
+                            key: FlexNodeKey::AccountAsset(FlexCompositeKey {
+                                e0: FlexKeyElem::That(args.supplier.clone()),
                                 e1: args.asset.clone(),
                             }),
-                            value: NodeValueWrite::AccountAsset(AccountAssetW::Send(
-                                args.supply_amount,
-                            )),
+                            value: NodeValueWrite::AccountAsset(AccountAssetW::Send(supply_amount)),
                         },
                     ]
                 })
@@ -72,10 +91,6 @@ impl Guest for SupplyAll {
 
         WriteSet { inner }
     }
-
-    fn write_approval(_signals: EventSet, _receptors: AllowSet) -> bool {
-        unimplemented!("boilerplate");
-    }
 }
 
 export!(SupplyAll);
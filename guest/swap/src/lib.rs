@@ -0,0 +1,99 @@
+// Generated exports flatten every record field into a parameter
+#![allow(clippy::too_many_arguments)]
+
+use common::types::*;
+use serde::Deserialize;
+
+wit_bindgen::generate!({
+    world: "instruction",
+    path: "../../wit",
+    with: {
+        "poc:wit/types": common::types,
+    },
+});
+
+struct Swap;
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    // The account whose holdings are swapped; the caller's own if omitted
+    account: Option<String>,
+    // Name of the asset to burn
+    from: String,
+    // Name of the asset to mint
+    to: String,
+    // Amount to burn and mint, in units of `10^-scale`
+    amount: u128,
+    // Decimal scale of the amount; whole units if omitted
+    #[serde(default)]
+    scale: u8,
+    // Timestamp after which the swap no longer applies
+    deadline: Option<u64>,
+}
+
+impl Guest for Swap {
+    fn read_request(_context: Context, _authority: AccountK, args: String) -> ReadSet {
+        let args: Args = serde_json::from_str(&args).expect("wrong args");
+
+        let inner = vec![ReadEntry {
+            key: FlexFuzzyNodeKey::AccountAsset(FlexFuzzyCompositeKey {
+                e0: Some(account(args.account)),
+                e1: Some(args.from),
+            }),
+            value: NodeValueRead::AccountAsset,
+        }];
+
+        ReadSet { inner }
+    }
+
+    fn write_request(
+        context: Context,
+        _authority: AccountK,
+        view: ViewSet,
+        args: String,
+    ) -> WriteSet {
+        let args: Args = serde_json::from_str(&args).expect("wrong args");
+
+        // Swap nothing once the deadline has passed
+        if args
+            .deadline
+            .is_some_and(|deadline| deadline < context.timestamp)
+        {
+            return WriteSet { inner: vec![] };
+        }
+
+        // Swap nothing unless the account holds enough to burn
+        let amount = Numeric::new(args.amount, args.scale);
+        let sufficient = view.inner.iter().any(|entry| {
+            matches!(&entry.value, NodeValueView::AccountAsset(value) if amount.compare(&value.balance).is_le())
+        });
+        if !sufficient {
+            return WriteSet { inner: vec![] };
+        }
+
+        let inner = vec![
+            WriteEntry {
+                key: FlexNodeKey::AccountAsset(FlexCompositeKey {
+                    e0: account(args.account.clone()),
+                    e1: args.from,
+                }),
+                value: NodeValueWrite::AccountAsset(AccountAssetW::Burn(amount)),
+            },
+            WriteEntry {
+                key: FlexNodeKey::AccountAsset(FlexCompositeKey {
+                    e0: account(args.account),
+                    e1: args.to,
+                }),
+                value: NodeValueWrite::AccountAsset(AccountAssetW::Mint(amount)),
+            },
+        ];
+
+        WriteSet { inner }
+    }
+}
+
+export!(Swap);
+
+fn account(account: Option<String>) -> FlexKeyElem {
+    account.map_or(FlexKeyElem::This, FlexKeyElem::That)
+}
@@ -1,23 +1,314 @@
+use crate::instruction::InstructionError;
 use crate::prelude::{self as host, Resolve, UnResolve};
+pub use authorizer::{Authorizer, AuthorizerPre};
+pub use instruction::{Instruction, InstructionPre, poc};
 use poc::wit::types::*;
+pub use poc::wit::types::{AllowSet, Context, EventSet, ReadSet, SingleKey, ViewSet, WriteSet};
 
-wasmtime::component::bindgen!({
-    world: "universe",
-    path: "../wit",
-    additional_derives: [Clone, PartialEq, Eq, PartialOrd, Ord],
-});
+mod instruction {
+    wasmtime::component::bindgen!({
+        world: "instruction",
+        path: "../wit",
+        additional_derives: [Clone, PartialEq, Eq, PartialOrd, Ord],
+    });
+}
+
+mod authorizer {
+    wasmtime::component::bindgen!({
+        world: "authorizer",
+        path: "../wit",
+        with: {
+            "poc:wit/types": super::instruction::poc::wit::types,
+        },
+    });
+}
+
+impl From<host::Context> for Context {
+    fn from(host_ty: host::Context) -> Self {
+        let host::Context { height, timestamp } = host_ty;
+        Context { height, timestamp }
+    }
+}
+
+impl From<host::AccountK> for SingleKey {
+    fn from(host_ty: host::AccountK) -> Self {
+        SingleKey { e0: host_ty.0 }
+    }
+}
+
+impl From<Numeric> for host::Numeric {
+    fn from(guest_ty: Numeric) -> Self {
+        let Numeric { hi, lo, scale } = guest_ty;
+        host::Numeric::new(u128::from(hi) << 64 | u128::from(lo), scale)
+    }
+}
+
+impl From<host::Numeric> for Numeric {
+    fn from(host_ty: host::Numeric) -> Self {
+        let host::Numeric { mantissa, scale } = host_ty;
+        Numeric {
+            hi: (mantissa >> 64) as u64,
+            lo: mantissa as u64,
+            scale,
+        }
+    }
+}
 
-impl From<ReadSet> for host::ReadSet {
-    fn from(guest_ty: ReadSet) -> Self {
+// --- Keys ---
+
+impl From<FlexKeyElem> for host::FlexKeyElem {
+    fn from(guest_ty: FlexKeyElem) -> Self {
+        match guest_ty {
+            FlexKeyElem::This => host::FlexKeyElem::This,
+            FlexKeyElem::That(that) => host::FlexKeyElem::That(that),
+        }
+    }
+}
+
+impl From<host::FlexKeyElem> for FlexKeyElem {
+    fn from(host_ty: host::FlexKeyElem) -> Self {
+        match host_ty {
+            host::FlexKeyElem::This => FlexKeyElem::This,
+            host::FlexKeyElem::That(that) => FlexKeyElem::That(that),
+        }
+    }
+}
+
+impl From<NodeKey> for host::NodeKey {
+    fn from(guest_ty: NodeKey) -> Self {
+        match guest_ty {
+            NodeKey::Account(k) => host::NodeKey::Account(host::SingleKey(k.e0)),
+            NodeKey::AssetDefinition(k) => host::NodeKey::AssetDefinition(host::SingleKey(k.e0)),
+            NodeKey::AccountAsset(k) => host::NodeKey::AccountAsset(host::CompositeKey(k.e0, k.e1)),
+            NodeKey::Permission(k) => host::NodeKey::Permission(host::SingleKey(k.e0)),
+            NodeKey::AccountPermission(k) => {
+                host::NodeKey::AccountPermission(host::CompositeKey(k.e0, k.e1))
+            }
+            NodeKey::Executable(k) => host::NodeKey::Executable(host::SingleKey(k.e0)),
+        }
+    }
+}
+
+impl From<host::NodeKey> for NodeKey {
+    fn from(host_ty: host::NodeKey) -> Self {
+        match host_ty {
+            host::NodeKey::Account(host::SingleKey(e0)) => NodeKey::Account(SingleKey { e0 }),
+            host::NodeKey::AssetDefinition(host::SingleKey(e0)) => {
+                NodeKey::AssetDefinition(SingleKey { e0 })
+            }
+            host::NodeKey::AccountAsset(host::CompositeKey(e0, e1)) => {
+                NodeKey::AccountAsset(CompositeKey { e0, e1 })
+            }
+            host::NodeKey::Permission(host::SingleKey(e0)) => NodeKey::Permission(SingleKey { e0 }),
+            host::NodeKey::AccountPermission(host::CompositeKey(e0, e1)) => {
+                NodeKey::AccountPermission(CompositeKey { e0, e1 })
+            }
+            host::NodeKey::Executable(host::SingleKey(e0)) => NodeKey::Executable(SingleKey { e0 }),
+        }
+    }
+}
+
+impl From<FlexNodeKey> for host::FlexNodeKey {
+    fn from(guest_ty: FlexNodeKey) -> Self {
+        match guest_ty {
+            FlexNodeKey::Account(k) => host::FlexNodeKey::Account(host::FlexSingleKey(k.e0.into())),
+            FlexNodeKey::AssetDefinition(k) => {
+                host::FlexNodeKey::AssetDefinition(host::SingleKey(k.e0))
+            }
+            FlexNodeKey::AccountAsset(k) => {
+                host::FlexNodeKey::AccountAsset(host::FlexCompositeKey(k.e0.into(), k.e1))
+            }
+            FlexNodeKey::Permission(k) => host::FlexNodeKey::Permission(host::SingleKey(k.e0)),
+            FlexNodeKey::AccountPermission(k) => {
+                host::FlexNodeKey::AccountPermission(host::FlexCompositeKey(k.e0.into(), k.e1))
+            }
+            FlexNodeKey::Executable(k) => host::FlexNodeKey::Executable(host::SingleKey(k.e0)),
+        }
+    }
+}
+
+impl From<host::NodeKey> for FlexNodeKey {
+    fn from(host_ty: host::NodeKey) -> Self {
+        match host_ty {
+            host::NodeKey::Account(host::SingleKey(e0)) => FlexNodeKey::Account(FlexSingleKey {
+                e0: FlexKeyElem::That(e0),
+            }),
+            host::NodeKey::AssetDefinition(host::SingleKey(e0)) => {
+                FlexNodeKey::AssetDefinition(SingleKey { e0 })
+            }
+            host::NodeKey::AccountAsset(host::CompositeKey(e0, e1)) => {
+                FlexNodeKey::AccountAsset(FlexCompositeKey {
+                    e0: FlexKeyElem::That(e0),
+                    e1,
+                })
+            }
+            host::NodeKey::Permission(host::SingleKey(e0)) => {
+                FlexNodeKey::Permission(SingleKey { e0 })
+            }
+            host::NodeKey::AccountPermission(host::CompositeKey(e0, e1)) => {
+                FlexNodeKey::AccountPermission(FlexCompositeKey {
+                    e0: FlexKeyElem::That(e0),
+                    e1,
+                })
+            }
+            host::NodeKey::Executable(host::SingleKey(e0)) => {
+                FlexNodeKey::Executable(SingleKey { e0 })
+            }
+        }
+    }
+}
+
+impl From<FlexFuzzyNodeKey> for host::FlexFuzzyNodeKey {
+    fn from(guest_ty: FlexFuzzyNodeKey) -> Self {
+        match guest_ty {
+            FlexFuzzyNodeKey::Account(k) => {
+                host::FlexFuzzyNodeKey::Account(host::FlexFuzzySingleKey(k.e0.map(Into::into)))
+            }
+            FlexFuzzyNodeKey::AssetDefinition(k) => {
+                host::FlexFuzzyNodeKey::AssetDefinition(host::FuzzySingleKey(k.e0))
+            }
+            FlexFuzzyNodeKey::AccountAsset(k) => host::FlexFuzzyNodeKey::AccountAsset(
+                host::FlexFuzzyCompositeKey(k.e0.map(Into::into), k.e1),
+            ),
+            FlexFuzzyNodeKey::Permission(k) => {
+                host::FlexFuzzyNodeKey::Permission(host::FuzzySingleKey(k.e0))
+            }
+            FlexFuzzyNodeKey::AccountPermission(k) => host::FlexFuzzyNodeKey::AccountPermission(
+                host::FlexFuzzyCompositeKey(k.e0.map(Into::into), k.e1),
+            ),
+            FlexFuzzyNodeKey::Executable(k) => {
+                host::FlexFuzzyNodeKey::Executable(host::FuzzySingleKey(k.e0))
+            }
+        }
+    }
+}
+
+impl From<host::FlexFuzzyNodeKey> for FlexFuzzyNodeKey {
+    fn from(host_ty: host::FlexFuzzyNodeKey) -> Self {
+        match host_ty {
+            host::FlexFuzzyNodeKey::Account(host::FlexFuzzySingleKey(e0)) => {
+                FlexFuzzyNodeKey::Account(FlexFuzzySingleKey {
+                    e0: e0.map(Into::into),
+                })
+            }
+            host::FlexFuzzyNodeKey::AssetDefinition(host::FuzzySingleKey(e0)) => {
+                FlexFuzzyNodeKey::AssetDefinition(FuzzySingleKey { e0 })
+            }
+            host::FlexFuzzyNodeKey::AccountAsset(host::FlexFuzzyCompositeKey(e0, e1)) => {
+                FlexFuzzyNodeKey::AccountAsset(FlexFuzzyCompositeKey {
+                    e0: e0.map(Into::into),
+                    e1,
+                })
+            }
+            host::FlexFuzzyNodeKey::Permission(host::FuzzySingleKey(e0)) => {
+                FlexFuzzyNodeKey::Permission(FuzzySingleKey { e0 })
+            }
+            host::FlexFuzzyNodeKey::AccountPermission(host::FlexFuzzyCompositeKey(e0, e1)) => {
+                FlexFuzzyNodeKey::AccountPermission(FlexFuzzyCompositeKey {
+                    e0: e0.map(Into::into),
+                    e1,
+                })
+            }
+            host::FlexFuzzyNodeKey::Executable(host::FuzzySingleKey(e0)) => {
+                FlexFuzzyNodeKey::Executable(FuzzySingleKey { e0 })
+            }
+        }
+    }
+}
+
+impl From<host::FuzzyNodeKey> for FlexFuzzyNodeKey {
+    fn from(host_ty: host::FuzzyNodeKey) -> Self {
+        match host_ty {
+            host::FuzzyNodeKey::Account(host::FuzzySingleKey(e0)) => {
+                FlexFuzzyNodeKey::Account(FlexFuzzySingleKey {
+                    e0: e0.map(FlexKeyElem::That),
+                })
+            }
+            host::FuzzyNodeKey::AssetDefinition(host::FuzzySingleKey(e0)) => {
+                FlexFuzzyNodeKey::AssetDefinition(FuzzySingleKey { e0 })
+            }
+            host::FuzzyNodeKey::AccountAsset(host::FuzzyCompositeKey(e0, e1)) => {
+                FlexFuzzyNodeKey::AccountAsset(FlexFuzzyCompositeKey {
+                    e0: e0.map(FlexKeyElem::That),
+                    e1,
+                })
+            }
+            host::FuzzyNodeKey::Permission(host::FuzzySingleKey(e0)) => {
+                FlexFuzzyNodeKey::Permission(FuzzySingleKey { e0 })
+            }
+            host::FuzzyNodeKey::AccountPermission(host::FuzzyCompositeKey(e0, e1)) => {
+                FlexFuzzyNodeKey::AccountPermission(FlexFuzzyCompositeKey {
+                    e0: e0.map(FlexKeyElem::That),
+                    e1,
+                })
+            }
+            host::FuzzyNodeKey::Executable(host::FuzzySingleKey(e0)) => {
+                FlexFuzzyNodeKey::Executable(FuzzySingleKey { e0 })
+            }
+        }
+    }
+}
+
+impl From<FuzzyNodeKey> for host::FlexFuzzyNodeKey {
+    fn from(guest_ty: FuzzyNodeKey) -> Self {
+        match guest_ty {
+            FuzzyNodeKey::Account(k) => host::FlexFuzzyNodeKey::Account(host::FlexFuzzySingleKey(
+                k.e0.map(UnResolve::unresolve),
+            )),
+            FuzzyNodeKey::AssetDefinition(k) => {
+                host::FlexFuzzyNodeKey::AssetDefinition(host::FuzzySingleKey(k.e0))
+            }
+            FuzzyNodeKey::AccountAsset(k) => host::FlexFuzzyNodeKey::AccountAsset(
+                host::FlexFuzzyCompositeKey(k.e0.map(UnResolve::unresolve), k.e1),
+            ),
+            FuzzyNodeKey::Permission(k) => {
+                host::FlexFuzzyNodeKey::Permission(host::FuzzySingleKey(k.e0))
+            }
+            FuzzyNodeKey::AccountPermission(k) => host::FlexFuzzyNodeKey::AccountPermission(
+                host::FlexFuzzyCompositeKey(k.e0.map(UnResolve::unresolve), k.e1),
+            ),
+            FuzzyNodeKey::Executable(k) => {
+                host::FlexFuzzyNodeKey::Executable(host::FuzzySingleKey(k.e0))
+            }
+        }
+    }
+}
+
+impl From<host::FuzzyNodeKey> for FuzzyNodeKey {
+    fn from(host_ty: host::FuzzyNodeKey) -> Self {
+        match host_ty {
+            host::FuzzyNodeKey::Account(host::FuzzySingleKey(e0)) => {
+                FuzzyNodeKey::Account(FuzzySingleKey { e0 })
+            }
+            host::FuzzyNodeKey::AssetDefinition(host::FuzzySingleKey(e0)) => {
+                FuzzyNodeKey::AssetDefinition(FuzzySingleKey { e0 })
+            }
+            host::FuzzyNodeKey::AccountAsset(host::FuzzyCompositeKey(e0, e1)) => {
+                FuzzyNodeKey::AccountAsset(FuzzyCompositeKey { e0, e1 })
+            }
+            host::FuzzyNodeKey::Permission(host::FuzzySingleKey(e0)) => {
+                FuzzyNodeKey::Permission(FuzzySingleKey { e0 })
+            }
+            host::FuzzyNodeKey::AccountPermission(host::FuzzyCompositeKey(e0, e1)) => {
+                FuzzyNodeKey::AccountPermission(FuzzyCompositeKey { e0, e1 })
+            }
+            host::FuzzyNodeKey::Executable(host::FuzzySingleKey(e0)) => {
+                FuzzyNodeKey::Executable(FuzzySingleKey { e0 })
+            }
+        }
+    }
+}
+
+// --- Read ---
+
+impl From<(ReadSet, host::AccountK)> for host::ReadSet {
+    fn from((guest_ty, authority): (ReadSet, host::AccountK)) -> Self {
         let inner = guest_ty
             .inner
             .into_iter()
             .map(|entry| {
-                let FuzzyNodeKey::AccountAsset(k) = entry.key;
-                (
-                    host::FuzzyNodeKey::AccountAsset(host::FuzzyCompositeKey(k.e0, k.e1)),
-                    host::NodeValue::AccountAsset(host::AccountAssetR),
-                )
+                let key = host::FlexFuzzyNodeKey::from(entry.key).resolve(authority.0.clone());
+                (key, entry.value.into())
             })
             .collect();
 
@@ -25,170 +316,479 @@ impl From<ReadSet> for host::ReadSet {
     }
 }
 
+impl From<NodeValueRead> for host::NodeValue<host::Read> {
+    fn from(guest_ty: NodeValueRead) -> Self {
+        match guest_ty {
+            NodeValueRead::Account => host::NodeValue::Account(host::AccountR),
+            NodeValueRead::AssetDefinition => {
+                host::NodeValue::AssetDefinition(host::AssetDefinitionR)
+            }
+            NodeValueRead::AccountAsset => host::NodeValue::AccountAsset(host::AccountAssetR),
+            NodeValueRead::Permission => host::NodeValue::Permission(host::PermissionR),
+            NodeValueRead::AccountPermission => {
+                host::NodeValue::AccountPermission(host::AccountPermissionR)
+            }
+            NodeValueRead::Executable => host::NodeValue::Executable(host::ExecutableR),
+        }
+    }
+}
+
 impl From<host::ReadSet> for ReadSet {
     fn from(host_ty: host::ReadSet) -> Self {
         let inner = host_ty
             .0
             .clone()
-            .into_keys()
-            .map(|key| {
-                let host::FuzzyNodeKey::AccountAsset(host::FuzzyCompositeKey(e0, e1)) = key;
-                ReadEntry {
-                    key: FuzzyNodeKey::AccountAsset(FuzzyCompositeKey { e0, e1 }),
-                    value: NodeValueRead::AccountAsset,
-                }
+            .into_iter()
+            .map(|(key, value)| ReadEntry {
+                key: key.into(),
+                value: match value {
+                    host::NodeValue::Account(host::AccountR) => NodeValueRead::Account,
+                    host::NodeValue::AssetDefinition(host::AssetDefinitionR) => {
+                        NodeValueRead::AssetDefinition
+                    }
+                    host::NodeValue::AccountAsset(host::AccountAssetR) => {
+                        NodeValueRead::AccountAsset
+                    }
+                    host::NodeValue::Permission(host::PermissionR) => NodeValueRead::Permission,
+                    host::NodeValue::AccountPermission(host::AccountPermissionR) => {
+                        NodeValueRead::AccountPermission
+                    }
+                    host::NodeValue::Executable(host::ExecutableR) => NodeValueRead::Executable,
+                },
             })
             .collect();
         ReadSet { inner }
     }
 }
 
-impl From<ViewSet> for host::ViewSet {
-    fn from(guest_ty: ViewSet) -> Self {
+// --- View ---
+
+// Views only flow from the host, which keeps compiled components a guest cannot hand back
+
+impl From<host::ViewSet> for ViewSet {
+    fn from(host_ty: host::ViewSet) -> Self {
+        let inner = host_ty
+            .0
+            .clone()
+            .into_iter()
+            .map(|(key, value)| ViewEntry {
+                key: key.into(),
+                value: match value {
+                    host::NodeValue::Account(host::AccountV) => NodeValueView::Account,
+                    host::NodeValue::AssetDefinition(host::AssetDefinitionV {
+                        owner,
+                        scale,
+                        total_supply,
+                    }) => NodeValueView::AssetDefinition(AssetDefinitionV {
+                        owner: owner.into(),
+                        scale,
+                        total_supply: total_supply.into(),
+                    }),
+                    host::NodeValue::AccountAsset(host::AccountAssetV { balance }) => {
+                        NodeValueView::AccountAsset(AccountAssetV {
+                            balance: balance.into(),
+                        })
+                    }
+                    host::NodeValue::Permission(permission) => {
+                        NodeValueView::Permission(permission.into())
+                    }
+                    host::NodeValue::AccountPermission(host::AccountPermissionV) => {
+                        NodeValueView::AccountPermission
+                    }
+                    host::NodeValue::Executable(host::ExecutableV { hash, .. }) => {
+                        NodeValueView::Executable(ExecutableV {
+                            hash: hash.to_vec(),
+                        })
+                    }
+                },
+            })
+            .collect();
+        ViewSet { inner }
+    }
+}
+
+impl From<PermissionV> for host::PermissionV {
+    fn from(guest_ty: PermissionV) -> Self {
         let inner = guest_ty
             .inner
             .into_iter()
-            .map(|entry| {
-                let NodeKey::AccountAsset(k) = entry.key;
-                let NodeValueView::AccountAsset(AccountAssetV { balance }) = entry.value;
-                (
-                    host::NodeKey::AccountAsset(host::CompositeKey(k.e0, k.e1)),
-                    host::NodeValue::AccountAsset(host::AccountAssetV { balance }),
-                )
-            })
+            .map(|entry| (entry.key.into(), entry.value.into()))
             .collect();
 
-        host::Tree(inner)
+        host::PermissionV {
+            permission: host::FlexFuzzyTree(inner),
+        }
     }
 }
 
-impl From<host::ViewSet> for ViewSet {
-    fn from(host_ty: host::ViewSet) -> Self {
+impl From<host::PermissionV> for PermissionV {
+    fn from(host_ty: host::PermissionV) -> Self {
         let inner = host_ty
+            .permission
             .0
-            .clone()
             .into_iter()
-            .map(|(key, value)| {
-                let host::NodeKey::AccountAsset(host::CompositeKey(e0, e1)) = key;
-                let host::NodeValue::AccountAsset(host::AccountAssetV { balance }) = value;
-                ViewEntry {
-                    key: NodeKey::AccountAsset(CompositeKey { e0, e1 }),
-                    value: NodeValueView::AccountAsset(AccountAssetV { balance }),
-                }
+            .map(|(key, value)| PermissionEntry {
+                key: key.into(),
+                value: value.into(),
             })
             .collect();
-        ViewSet { inner }
+        PermissionV { inner }
     }
 }
 
-impl From<WriteSet> for host::WriteSet {
-    fn from(guest_ty: WriteSet) -> Self {
-        let mut inner = guest_ty.inner;
-        inner.sort_unstable();
-        inner.dedup_by(|a, b| {
-            a.key == b.key
-                && match (&a.value, &mut b.value) {
-                    (
-                        NodeValueWrite::AccountAsset(AccountAssetW::Receive(a)),
-                        NodeValueWrite::AccountAsset(AccountAssetW::Receive(b)),
-                    ) => {
-                        *b += a;
-                        true
-                    }
-                    (
-                        NodeValueWrite::AccountAsset(AccountAssetW::Send(a)),
-                        NodeValueWrite::AccountAsset(AccountAssetW::Send(b)),
-                    ) => {
-                        *b += a;
-                        true
-                    }
-                    _ => panic!(
-                        "WriteSet aggregation failed: {:?} and {:?} are not compatible",
-                        a.value, b.value
-                    ),
-                }
-        });
-        let inner = inner
+// --- Write ---
+
+impl From<NodeValueWrite> for host::NodeValue<host::Write> {
+    fn from(guest_ty: NodeValueWrite) -> Self {
+        match guest_ty {
+            NodeValueWrite::Account(AccountW::Register) => {
+                host::NodeValue::Account(host::AccountW::Register)
+            }
+            NodeValueWrite::Account(AccountW::Unregister) => {
+                host::NodeValue::Account(host::AccountW::Unregister)
+            }
+            NodeValueWrite::AssetDefinition(AssetDefinitionW::Register(scale)) => {
+                host::NodeValue::AssetDefinition(host::AssetDefinitionW::Register { scale })
+            }
+            NodeValueWrite::AssetDefinition(AssetDefinitionW::Unregister) => {
+                host::NodeValue::AssetDefinition(host::AssetDefinitionW::Unregister)
+            }
+            NodeValueWrite::AccountAsset(AccountAssetW::Receive(amount)) => {
+                host::NodeValue::AccountAsset(host::AccountAssetW::Receive(amount.into()))
+            }
+            NodeValueWrite::AccountAsset(AccountAssetW::Send(amount)) => {
+                host::NodeValue::AccountAsset(host::AccountAssetW::Send(amount.into()))
+            }
+            NodeValueWrite::AccountAsset(AccountAssetW::Mint(amount)) => {
+                host::NodeValue::AccountAsset(host::AccountAssetW::Mint(amount.into()))
+            }
+            NodeValueWrite::AccountAsset(AccountAssetW::Burn(amount)) => {
+                host::NodeValue::AccountAsset(host::AccountAssetW::Burn(amount.into()))
+            }
+            NodeValueWrite::Permission(PermissionW::Define(permission)) => {
+                host::NodeValue::Permission(host::PermissionW::Define(permission.into()))
+            }
+            NodeValueWrite::Permission(PermissionW::Redefine(permission)) => {
+                host::NodeValue::Permission(host::PermissionW::Redefine(permission.into()))
+            }
+            NodeValueWrite::Permission(PermissionW::Delete) => {
+                host::NodeValue::Permission(host::PermissionW::Delete)
+            }
+            NodeValueWrite::AccountPermission(AccountPermissionW::Grant) => {
+                host::NodeValue::AccountPermission(host::AccountPermissionW::Grant)
+            }
+            NodeValueWrite::AccountPermission(AccountPermissionW::Revoke) => {
+                host::NodeValue::AccountPermission(host::AccountPermissionW::Revoke)
+            }
+            NodeValueWrite::Executable(ExecutableW::Register(bytes)) => {
+                host::NodeValue::Executable(host::ExecutableW::Register(bytes))
+            }
+            NodeValueWrite::Executable(ExecutableW::Upgrade(bytes)) => {
+                host::NodeValue::Executable(host::ExecutableW::Upgrade(bytes))
+            }
+            NodeValueWrite::Executable(ExecutableW::Unregister) => {
+                host::NodeValue::Executable(host::ExecutableW::Unregister)
+            }
+        }
+    }
+}
+
+impl From<host::NodeValue<host::Write>> for NodeValueWrite {
+    fn from(host_ty: host::NodeValue<host::Write>) -> Self {
+        match host_ty {
+            host::NodeValue::Account(host::AccountW::Register) => {
+                NodeValueWrite::Account(AccountW::Register)
+            }
+            host::NodeValue::Account(host::AccountW::Unregister) => {
+                NodeValueWrite::Account(AccountW::Unregister)
+            }
+            host::NodeValue::AssetDefinition(host::AssetDefinitionW::Register { scale }) => {
+                NodeValueWrite::AssetDefinition(AssetDefinitionW::Register(scale))
+            }
+            host::NodeValue::AssetDefinition(host::AssetDefinitionW::Unregister) => {
+                NodeValueWrite::AssetDefinition(AssetDefinitionW::Unregister)
+            }
+            host::NodeValue::AccountAsset(host::AccountAssetW::Receive(amount)) => {
+                NodeValueWrite::AccountAsset(AccountAssetW::Receive(amount.into()))
+            }
+            host::NodeValue::AccountAsset(host::AccountAssetW::Send(amount)) => {
+                NodeValueWrite::AccountAsset(AccountAssetW::Send(amount.into()))
+            }
+            host::NodeValue::AccountAsset(host::AccountAssetW::Mint(amount)) => {
+                NodeValueWrite::AccountAsset(AccountAssetW::Mint(amount.into()))
+            }
+            host::NodeValue::AccountAsset(host::AccountAssetW::Burn(amount)) => {
+                NodeValueWrite::AccountAsset(AccountAssetW::Burn(amount.into()))
+            }
+            host::NodeValue::Permission(host::PermissionW::Define(permission)) => {
+                NodeValueWrite::Permission(PermissionW::Define(permission.into()))
+            }
+            host::NodeValue::Permission(host::PermissionW::Redefine(permission)) => {
+                NodeValueWrite::Permission(PermissionW::Redefine(permission.into()))
+            }
+            host::NodeValue::Permission(host::PermissionW::Delete) => {
+                NodeValueWrite::Permission(PermissionW::Delete)
+            }
+            host::NodeValue::AccountPermission(host::AccountPermissionW::Grant) => {
+                NodeValueWrite::AccountPermission(AccountPermissionW::Grant)
+            }
+            host::NodeValue::AccountPermission(host::AccountPermissionW::Revoke) => {
+                NodeValueWrite::AccountPermission(AccountPermissionW::Revoke)
+            }
+            host::NodeValue::Executable(host::ExecutableW::Register(bytes)) => {
+                NodeValueWrite::Executable(ExecutableW::Register(bytes))
+            }
+            host::NodeValue::Executable(host::ExecutableW::Upgrade(bytes)) => {
+                NodeValueWrite::Executable(ExecutableW::Upgrade(bytes))
+            }
+            host::NodeValue::Executable(host::ExecutableW::Unregister) => {
+                NodeValueWrite::Executable(ExecutableW::Unregister)
+            }
+        }
+    }
+}
+
+impl TryFrom<(WriteSet, host::AccountK)> for host::WriteSet {
+    type Error = InstructionError;
+
+    fn try_from((guest_ty, authority): (WriteSet, host::AccountK)) -> Result<Self, Self::Error> {
+        // Resolve before aggregating, so that intents on `This` and on the authority itself merge
+        let mut inner: Vec<WriteEntry> = guest_ty
+            .inner
             .into_iter()
-            .map(|entry| {
-                let NodeKey::AccountAsset(k) = entry.key;
-                let value = match entry.value {
-                    NodeValueWrite::AccountAsset(AccountAssetW::Receive(amount)) => {
-                        host::NodeValue::AccountAsset(host::AccountAssetW::Receive(amount))
-                    }
-                    NodeValueWrite::AccountAsset(AccountAssetW::Send(amount)) => {
-                        host::NodeValue::AccountAsset(host::AccountAssetW::Send(amount))
+            .map(|mut entry| {
+                match &mut entry.key {
+                    FlexNodeKey::Account(FlexSingleKey { e0 })
+                    | FlexNodeKey::AccountAsset(FlexCompositeKey { e0, .. })
+                    | FlexNodeKey::AccountPermission(FlexCompositeKey { e0, .. }) => {
+                        if let FlexKeyElem::This = e0 {
+                            *e0 = FlexKeyElem::That(authority.0.clone());
+                        }
                     }
-                };
-                (
-                    host::FlexNodeKey::AccountAsset(host::FlexCompositeKey(
-                        host::FlexKeyElem::That(k.e0),
-                        k.e1,
-                    )),
-                    value,
-                )
+                    FlexNodeKey::AssetDefinition(_)
+                    | FlexNodeKey::Permission(_)
+                    | FlexNodeKey::Executable(_) => {}
+                }
+                entry
             })
             .collect();
+        inner.sort_unstable();
+        let mut aggregated: Vec<WriteEntry> = Vec::with_capacity(inner.len());
+        for entry in inner {
+            match aggregated.last_mut() {
+                Some(last) if last.key == entry.key => aggregate(last, entry)?,
+                _ => aggregated.push(entry),
+            }
+        }
+        let inner = aggregated
+            .into_iter()
+            .map(|entry| match (&entry.key, &entry.value) {
+                (FlexNodeKey::Account(_), NodeValueWrite::Account(_))
+                | (FlexNodeKey::AssetDefinition(_), NodeValueWrite::AssetDefinition(_))
+                | (FlexNodeKey::AccountAsset(_), NodeValueWrite::AccountAsset(_))
+                | (FlexNodeKey::Permission(_), NodeValueWrite::Permission(_))
+                | (FlexNodeKey::AccountPermission(_), NodeValueWrite::AccountPermission(_))
+                | (FlexNodeKey::Executable(_), NodeValueWrite::Executable(_)) => {
+                    Ok((entry.key.into(), entry.value.into()))
+                }
+                _ => Err(InstructionError::InvalidGuestData(format!(
+                    "WriteSet entry at {:?} holds {:?} of another node type",
+                    entry.key, entry.value
+                ))),
+            })
+            .collect::<Result<_, _>>()?;
 
-        host::FlexTree(inner)
+        Ok(host::FlexTree(inner))
     }
 }
 
+/// Merges a duplicate write intent into the preceding one for the same key.
+fn aggregate(acc: &mut WriteEntry, entry: WriteEntry) -> Result<(), InstructionError> {
+    let (a, b) = match (&entry.value, &mut acc.value) {
+        (
+            NodeValueWrite::AccountAsset(AccountAssetW::Receive(a)),
+            NodeValueWrite::AccountAsset(AccountAssetW::Receive(b)),
+        )
+        | (
+            NodeValueWrite::AccountAsset(AccountAssetW::Send(a)),
+            NodeValueWrite::AccountAsset(AccountAssetW::Send(b)),
+        )
+        | (
+            NodeValueWrite::AccountAsset(AccountAssetW::Mint(a)),
+            NodeValueWrite::AccountAsset(AccountAssetW::Mint(b)),
+        )
+        | (
+            NodeValueWrite::AccountAsset(AccountAssetW::Burn(a)),
+            NodeValueWrite::AccountAsset(AccountAssetW::Burn(b)),
+        ) => (a, b),
+        _ => {
+            return Err(InstructionError::InvalidGuestData(format!(
+                "WriteSet aggregation failed: {:?} and {:?} are not compatible",
+                entry.value, acc.value
+            )));
+        }
+    };
+    let sum = host::Numeric::from(*b)
+        .checked_add((*a).into())
+        .ok_or_else(|| {
+            InstructionError::InvalidGuestData(format!(
+                "WriteSet aggregation overflowed at {:?}",
+                entry.key
+            ))
+        })?;
+    *b = sum.into();
+
+    Ok(())
+}
+
 impl From<(host::WriteSet, host::AccountK)> for WriteSet {
     fn from((host_ty, authority): (host::WriteSet, host::AccountK)) -> Self {
         let inner = host_ty
             .0
             .clone()
             .into_iter()
-            .map(|(key, value)| {
-                let host::NodeKey::AccountAsset(host::CompositeKey(e0, e1)) =
-                    key.resolve(authority.0.clone());
-                let value = match value {
-                    host::NodeValue::AccountAsset(host::AccountAssetW::Receive(amount)) => {
-                        NodeValueWrite::AccountAsset(AccountAssetW::Receive(amount))
-                    }
-                    host::NodeValue::AccountAsset(host::AccountAssetW::Send(amount)) => {
-                        NodeValueWrite::AccountAsset(AccountAssetW::Send(amount))
-                    }
-                };
-                WriteEntry {
-                    key: NodeKey::AccountAsset(CompositeKey { e0, e1 }),
-                    value,
-                }
+            .map(|(key, value)| WriteEntry {
+                key: key.resolve(authority.0.clone()).into(),
+                value: value.into(),
             })
             .collect();
         WriteSet { inner }
     }
 }
 
-impl From<EventSet> for host::EventSet {
-    fn from(guest_ty: EventSet) -> Self {
+// --- Event ---
+
+impl TryFrom<EventSet> for host::EventSet {
+    type Error = InstructionError;
+
+    fn try_from(guest_ty: EventSet) -> Result<Self, Self::Error> {
         let inner = guest_ty
             .inner
             .into_iter()
-            .map(|entry| {
-                let NodeKey::AccountAsset(k) = entry.key;
-                let NodeValueEvent::AccountAsset(status) = entry.value;
-                (
-                    host::NodeKey::AccountAsset(host::CompositeKey(k.e0, k.e1)),
-                    host::NodeValue::AccountAsset(status.into()),
-                )
-            })
-            .collect();
+            .map(|entry| Ok((entry.key.into(), entry.value.try_into()?)))
+            .collect::<Result<_, _>>()?;
+
+        Ok(host::Tree(inner))
+    }
+}
 
-        host::Tree(inner)
+impl TryFrom<NodeValueEvent> for host::NodeValue<host::Event> {
+    type Error = InstructionError;
+
+    fn try_from(guest_ty: NodeValueEvent) -> Result<Self, Self::Error> {
+        Ok(match guest_ty {
+            NodeValueEvent::Account(status) => host::NodeValue::Account(status.try_into()?),
+            NodeValueEvent::AssetDefinition(status) => {
+                host::NodeValue::AssetDefinition(status.try_into()?)
+            }
+            NodeValueEvent::AccountAsset(status) => {
+                host::NodeValue::AccountAsset(status.try_into()?)
+            }
+            NodeValueEvent::Permission(status) => host::NodeValue::Permission(status.try_into()?),
+            NodeValueEvent::AccountPermission(status) => {
+                host::NodeValue::AccountPermission(status.try_into()?)
+            }
+            NodeValueEvent::Executable(status) => host::NodeValue::Executable(status.try_into()?),
+        })
+    }
+}
+
+impl TryFrom<AccountE> for host::AccountE {
+    type Error = InstructionError;
+
+    fn try_from(e: AccountE) -> Result<Self, Self::Error> {
+        match e.status_bit {
+            0b0000_0001 => Ok(host::AccountE::Read),
+            0b0000_0010 => Ok(host::AccountE::Register),
+            0b0000_0100 => Ok(host::AccountE::Unregister),
+            _ => Err(InstructionError::InvalidGuestData(format!(
+                "Invalid AccountE status bit: {:08b}",
+                e.status_bit
+            ))),
+        }
+    }
+}
+
+impl TryFrom<AssetDefinitionE> for host::AssetDefinitionE {
+    type Error = InstructionError;
+
+    fn try_from(e: AssetDefinitionE) -> Result<Self, Self::Error> {
+        match e.status_bit {
+            0b0000_0001 => Ok(host::AssetDefinitionE::Read),
+            0b0000_0010 => Ok(host::AssetDefinitionE::Register),
+            0b0000_0100 => Ok(host::AssetDefinitionE::Unregister),
+            _ => Err(InstructionError::InvalidGuestData(format!(
+                "Invalid AssetDefinitionE status bit: {:08b}",
+                e.status_bit
+            ))),
+        }
     }
 }
 
-impl From<AccountAssetE> for host::AccountAssetE {
-    fn from(e: AccountAssetE) -> Self {
+impl TryFrom<AccountAssetE> for host::AccountAssetE {
+    type Error = InstructionError;
+
+    fn try_from(e: AccountAssetE) -> Result<Self, Self::Error> {
         match e.status_bit {
-            0b0000_0001 => host::AccountAssetE::Read,
-            0b0000_0010 => host::AccountAssetE::Receive,
-            0b0000_0100 => host::AccountAssetE::Send,
-            0b0001_0000 => host::AccountAssetE::Mint,
-            0b0010_0000 => host::AccountAssetE::Burn,
-            _ => panic!("Invalid AccountAssetE status bit: {:08b}", e.status_bit),
+            0b0000_0001 => Ok(host::AccountAssetE::Read),
+            0b0000_0010 => Ok(host::AccountAssetE::Receive),
+            0b0000_0100 => Ok(host::AccountAssetE::Send),
+            0b0001_0000 => Ok(host::AccountAssetE::Mint),
+            0b0010_0000 => Ok(host::AccountAssetE::Burn),
+            _ => Err(InstructionError::InvalidGuestData(format!(
+                "Invalid AccountAssetE status bit: {:08b}",
+                e.status_bit
+            ))),
+        }
+    }
+}
+
+impl TryFrom<PermissionE> for host::PermissionE {
+    type Error = InstructionError;
+
+    fn try_from(e: PermissionE) -> Result<Self, Self::Error> {
+        match e.status_bit {
+            0b0000_0001 => Ok(host::PermissionE::Read),
+            0b0000_0010 => Ok(host::PermissionE::Define),
+            0b0000_0100 => Ok(host::PermissionE::Delete),
+            0b0000_1000 => Ok(host::PermissionE::Redefine),
+            _ => Err(InstructionError::InvalidGuestData(format!(
+                "Invalid PermissionE status bit: {:08b}",
+                e.status_bit
+            ))),
+        }
+    }
+}
+
+impl TryFrom<AccountPermissionE> for host::AccountPermissionE {
+    type Error = InstructionError;
+
+    fn try_from(e: AccountPermissionE) -> Result<Self, Self::Error> {
+        match e.status_bit {
+            0b0000_0001 => Ok(host::AccountPermissionE::Read),
+            0b0000_0010 => Ok(host::AccountPermissionE::Grant),
+            0b0000_0100 => Ok(host::AccountPermissionE::Revoke),
+            _ => Err(InstructionError::InvalidGuestData(format!(
+                "Invalid AccountPermissionE status bit: {:08b}",
+                e.status_bit
+            ))),
+        }
+    }
+}
+
+impl TryFrom<ExecutableE> for host::ExecutableE {
+    type Error = InstructionError;
+
+    fn try_from(e: ExecutableE) -> Result<Self, Self::Error> {
+        match e.status_bit {
+            0b0000_0001 => Ok(host::ExecutableE::Read),
+            0b0000_0010 => Ok(host::ExecutableE::Register),
+            0b0000_0100 => Ok(host::ExecutableE::Unregister),
+            0b0000_1000 => Ok(host::ExecutableE::Upgrade),
+            _ => Err(InstructionError::InvalidGuestData(format!(
+                "Invalid ExecutableE status bit: {:08b}",
+                e.status_bit
+            ))),
         }
     }
 }
@@ -199,15 +799,38 @@ impl From<host::EventSet> for EventSet {
             .0
             .clone()
             .into_iter()
-            .map(|(key, value)| {
-                let host::NodeKey::AccountAsset(host::CompositeKey(e0, e1)) = key;
-                let host::NodeValue::AccountAsset(status) = value;
-                EventEntry {
-                    key: NodeKey::AccountAsset(CompositeKey { e0, e1 }),
-                    value: NodeValueEvent::AccountAsset(AccountAssetE {
+            .map(|(key, value)| EventEntry {
+                key: key.into(),
+                value: match value {
+                    host::NodeValue::Account(status) => NodeValueEvent::Account(AccountE {
                         status_bit: status as u8,
                     }),
-                }
+                    host::NodeValue::AssetDefinition(status) => {
+                        NodeValueEvent::AssetDefinition(AssetDefinitionE {
+                            status_bit: status as u8,
+                        })
+                    }
+                    host::NodeValue::AccountAsset(status) => {
+                        NodeValueEvent::AccountAsset(AccountAssetE {
+                            status_bit: status as u8,
+                        })
+                    }
+                    host::NodeValue::Permission(status) => {
+                        NodeValueEvent::Permission(PermissionE {
+                            status_bit: status as u8,
+                        })
+                    }
+                    host::NodeValue::AccountPermission(status) => {
+                        NodeValueEvent::AccountPermission(AccountPermissionE {
+                            status_bit: status as u8,
+                        })
+                    }
+                    host::NodeValue::Executable(status) => {
+                        NodeValueEvent::Executable(ExecutableE {
+                            status_bit: status as u8,
+                        })
+                    }
+                },
             })
             .collect();
 
@@ -215,22 +838,64 @@ impl From<host::EventSet> for EventSet {
     }
 }
 
+// --- Allow ---
+
+impl From<NodeValueAllow> for host::NodeValue<host::Allow> {
+    fn from(guest_ty: NodeValueAllow) -> Self {
+        match guest_ty {
+            NodeValueAllow::Account(AccountA { bit_mask }) => {
+                host::NodeValue::Account(host::AccountA { bit_mask })
+            }
+            NodeValueAllow::AssetDefinition(AssetDefinitionA { bit_mask }) => {
+                host::NodeValue::AssetDefinition(host::AssetDefinitionA { bit_mask })
+            }
+            NodeValueAllow::AccountAsset(AccountAssetA { bit_mask }) => {
+                host::NodeValue::AccountAsset(host::AccountAssetA { bit_mask })
+            }
+            NodeValueAllow::Permission(PermissionA { bit_mask }) => {
+                host::NodeValue::Permission(host::PermissionA { bit_mask })
+            }
+            NodeValueAllow::AccountPermission(AccountPermissionA { bit_mask }) => {
+                host::NodeValue::AccountPermission(host::AccountPermissionA { bit_mask })
+            }
+            NodeValueAllow::Executable(ExecutableA { bit_mask }) => {
+                host::NodeValue::Executable(host::ExecutableA { bit_mask })
+            }
+        }
+    }
+}
+
+impl From<host::NodeValue<host::Allow>> for NodeValueAllow {
+    fn from(host_ty: host::NodeValue<host::Allow>) -> Self {
+        match host_ty {
+            host::NodeValue::Account(host::AccountA { bit_mask }) => {
+                NodeValueAllow::Account(AccountA { bit_mask })
+            }
+            host::NodeValue::AssetDefinition(host::AssetDefinitionA { bit_mask }) => {
+                NodeValueAllow::AssetDefinition(AssetDefinitionA { bit_mask })
+            }
+            host::NodeValue::AccountAsset(host::AccountAssetA { bit_mask }) => {
+                NodeValueAllow::AccountAsset(AccountAssetA { bit_mask })
+            }
+            host::NodeValue::Permission(host::PermissionA { bit_mask }) => {
+                NodeValueAllow::Permission(PermissionA { bit_mask })
+            }
+            host::NodeValue::AccountPermission(host::AccountPermissionA { bit_mask }) => {
+                NodeValueAllow::AccountPermission(AccountPermissionA { bit_mask })
+            }
+            host::NodeValue::Executable(host::ExecutableA { bit_mask }) => {
+                NodeValueAllow::Executable(ExecutableA { bit_mask })
+            }
+        }
+    }
+}
+
 impl From<AllowSet> for host::AllowSet {
     fn from(guest_ty: AllowSet) -> Self {
         let inner = guest_ty
             .inner
             .into_iter()
-            .map(|entry| {
-                let FuzzyNodeKey::AccountAsset(k) = entry.key;
-                let NodeValueAllow::AccountAsset(AccountAssetA { bit_mask }) = entry.value;
-                (
-                    host::FlexFuzzyNodeKey::AccountAsset(host::FlexFuzzyCompositeKey(
-                        k.e0.map(UnResolve::unresolve),
-                        k.e1,
-                    )),
-                    host::NodeValue::AccountAsset(host::AccountAssetA { bit_mask }),
-                )
-            })
+            .map(|entry| (entry.key.into(), entry.value.into()))
             .collect();
 
         host::FlexFuzzyTree(inner)
@@ -243,55 +908,43 @@ impl From<(host::AllowSet, host::AccountK)> for AllowSet {
             .0
             .clone()
             .into_iter()
-            .map(|(key, value)| {
-                let host::FlexFuzzyNodeKey::AccountAsset(host::FlexFuzzyCompositeKey(e0, e1)) = key;
-                let host::NodeValue::AccountAsset(host::AccountAssetA { bit_mask }) = value;
-                AllowEntry {
-                    key: FuzzyNodeKey::AccountAsset(FuzzyCompositeKey {
-                        e0: e0.map(|elem| elem.resolve(authority.0.clone())),
-                        e1,
-                    }),
-                    value: NodeValueAllow::AccountAsset(AccountAssetA { bit_mask }),
-                }
+            .map(|(key, value)| AllowEntry {
+                key: key.resolve(authority.0.clone()).into(),
+                value: value.into(),
             })
             .collect();
         AllowSet { inner }
     }
 }
 
-impl From<&WriteSet> for EventSet {
-    fn from(write_set: &WriteSet) -> Self {
-        let inner = write_set
-            .inner
-            .iter()
-            .map(|entry| {
-                let value = match entry.value {
-                    NodeValueWrite::AccountAsset(AccountAssetW::Send(_)) => {
-                        NodeValueEvent::AccountAsset(AccountAssetE {
-                            status_bit: 0b0000_00100,
-                        })
-                    }
-                    NodeValueWrite::AccountAsset(AccountAssetW::Receive(_)) => {
-                        NodeValueEvent::AccountAsset(AccountAssetE {
-                            status_bit: 0b0000_00010,
-                        })
-                    }
-                };
-                EventEntry {
-                    key: entry.key.clone(),
-                    value,
-                }
-            })
-            .collect();
+// --- Verdict ---
 
-        EventSet { inner }
+impl From<(ReadRejection, host::AccountK)> for host::ReadRejection {
+    fn from((guest_ty, authority): (ReadRejection, host::AccountK)) -> Self {
+        Self {
+            key: host::FlexFuzzyNodeKey::from(guest_ty.entry.key).resolve(authority.0),
+            value: guest_ty.entry.value.into(),
+            required: guest_ty.required,
+            granted: guest_ty.granted,
+        }
+    }
+}
+
+impl TryFrom<WriteRejection> for host::WriteRejection {
+    type Error = InstructionError;
+
+    fn try_from(guest_ty: WriteRejection) -> Result<Self, Self::Error> {
+        Ok(Self {
+            key: guest_ty.entry.key.into(),
+            value: guest_ty.entry.value.try_into()?,
+            required: guest_ty.required,
+            granted: guest_ty.granted,
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::prelude::{FlexCompositeKey, FlexKeyElem};
-
     use super::*;
 
     #[test]
@@ -299,56 +952,123 @@ mod tests {
         let write_set = WriteSet {
             inner: vec![
                 WriteEntry {
-                    key: NodeKey::AccountAsset(CompositeKey {
-                        e0: "alice".to_string(),
+                    key: FlexNodeKey::AccountAsset(FlexCompositeKey {
+                        e0: FlexKeyElem::That("alice".to_string()),
                         e1: "rose".to_string(),
                     }),
-                    value: NodeValueWrite::AccountAsset(AccountAssetW::Receive(10)),
+                    value: NodeValueWrite::AccountAsset(AccountAssetW::Receive(
+                        host::Numeric::from(10).into(),
+                    )),
                 },
                 WriteEntry {
-                    key: NodeKey::AccountAsset(CompositeKey {
-                        e0: "alice".to_string(),
+                    key: FlexNodeKey::AccountAsset(FlexCompositeKey {
+                        e0: FlexKeyElem::That("alice".to_string()),
                         e1: "rose".to_string(),
                     }),
-                    value: NodeValueWrite::AccountAsset(AccountAssetW::Receive(20)),
+                    value: NodeValueWrite::AccountAsset(AccountAssetW::Receive(
+                        host::Numeric::from(20).into(),
+                    )),
                 },
             ],
         };
 
-        let host_write_set: host::WriteSet = write_set.into();
-        let key = host::FlexNodeKey::AccountAsset(FlexCompositeKey(
-            FlexKeyElem::That("alice".to_string()),
+        let host_write_set = host::WriteSet::try_from((write_set, host::SingleKey("alice".into())))
+            .expect("should aggregate");
+        let key = host::FlexNodeKey::AccountAsset(host::FlexCompositeKey(
+            host::FlexKeyElem::That("alice".to_string()),
             "rose".to_string(),
         ));
         assert_eq!(host_write_set.0.len(), 1);
         assert_eq!(
             host_write_set.0[&key],
-            host::NodeValue::AccountAsset(host::AccountAssetW::Receive(30))
+            host::NodeValue::AccountAsset(host::AccountAssetW::Receive(30.into()))
         );
     }
 
     #[test]
-    #[should_panic(expected = "WriteSet aggregation failed")]
     fn write_set_with_contradictory_intents_does_not_aggregate() {
         let write_set = WriteSet {
             inner: vec![
                 WriteEntry {
-                    key: NodeKey::AccountAsset(CompositeKey {
-                        e0: "alice".to_string(),
+                    key: FlexNodeKey::AccountAsset(FlexCompositeKey {
+                        e0: FlexKeyElem::That("alice".to_string()),
                         e1: "rose".to_string(),
                     }),
-                    value: NodeValueWrite::AccountAsset(AccountAssetW::Receive(10)),
+                    value: NodeValueWrite::AccountAsset(AccountAssetW::Receive(
+                        host::Numeric::from(10).into(),
+                    )),
                 },
                 WriteEntry {
-                    key: NodeKey::AccountAsset(CompositeKey {
-                        e0: "alice".to_string(),
+                    key: FlexNodeKey::AccountAsset(FlexCompositeKey {
+                        e0: FlexKeyElem::That("alice".to_string()),
                         e1: "rose".to_string(),
                     }),
-                    value: NodeValueWrite::AccountAsset(AccountAssetW::Send(20)),
+                    value: NodeValueWrite::AccountAsset(AccountAssetW::Send(
+                        host::Numeric::from(20).into(),
+                    )),
                 },
             ],
         };
 
-        let _host_write_set: host::WriteSet = write_set.into();
+        let res = host::WriteSet::try_from((write_set, host::SingleKey("alice".into())));
+        assert!(matches!(res, Err(InstructionError::InvalidGuestData(_))));
+    }
+
+    #[test]
+    fn write_set_resolves_this_before_aggregating() {
+        let write_set = WriteSet {
+            inner: vec![
+                WriteEntry {
+                    key: FlexNodeKey::AccountAsset(FlexCompositeKey {
+                        e0: FlexKeyElem::This,
+                        e1: "rose".to_string(),
+                    }),
+                    value: NodeValueWrite::AccountAsset(AccountAssetW::Send(
+                        host::Numeric::from(10).into(),
+                    )),
+                },
+                WriteEntry {
+                    key: FlexNodeKey::AccountAsset(FlexCompositeKey {
+                        e0: FlexKeyElem::That("alice".to_string()),
+                        e1: "rose".to_string(),
+                    }),
+                    value: NodeValueWrite::AccountAsset(AccountAssetW::Send(
+                        host::Numeric::from(20).into(),
+                    )),
+                },
+            ],
+        };
+
+        let host_write_set = host::WriteSet::try_from((write_set, host::SingleKey("alice".into())))
+            .expect("should aggregate");
+        let key = host::FlexNodeKey::AccountAsset(host::FlexCompositeKey(
+            host::FlexKeyElem::That("alice".to_string()),
+            "rose".to_string(),
+        ));
+        assert_eq!(host_write_set.0.len(), 1);
+        assert_eq!(
+            host_write_set.0[&key],
+            host::NodeValue::AccountAsset(host::AccountAssetW::Send(30.into()))
+        );
+    }
+
+    #[test]
+    fn write_rejection_with_undefined_status_bit_is_invalid() {
+        let rejection = WriteRejection {
+            entry: EventEntry {
+                key: NodeKey::AccountAsset(CompositeKey {
+                    e0: "alice".to_string(),
+                    e1: "rose".to_string(),
+                }),
+                value: NodeValueEvent::AccountAsset(AccountAssetE {
+                    status_bit: 0b0000_1000,
+                }),
+            },
+            required: 0b0000_1000,
+            granted: 0,
+        };
+
+        let res = host::WriteRejection::try_from(rejection);
+        assert!(matches!(res, Err(InstructionError::InvalidGuestData(_))));
     }
 }
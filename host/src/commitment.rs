@@ -0,0 +1,208 @@
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::prelude::*;
+use crate::state::{StateDiff, World};
+
+/// Bits in the path of a leaf, which is the hash of its key.
+const DEPTH: usize = 256;
+
+/// Hash of an empty subtree at each depth, from the root at 0 down to the leaves at [`DEPTH`].
+static EMPTY: LazyLock<Vec<Hash>> = LazyLock::new(|| {
+    let mut empty = vec![[0; 32]; DEPTH + 1];
+    for depth in (0..DEPTH).rev() {
+        empty[depth] = branch(&empty[depth + 1], &empty[depth + 1]);
+    }
+    empty
+});
+
+/// Sparse Merkle tree committing to every node of the world.
+///
+/// Each entry is a leaf at the path given by the hash of its key, so the root depends on the
+/// entries alone and not on the order they were written in:
+///
+/// ```text
+/// path   = sha256(json(key))
+/// leaf   = sha256(0x00 || json(key) || json(value))    executables valued by hash
+/// branch = sha256(0x01 || left || right)
+/// ```
+///
+/// Only non-empty nodes are kept, and a commit rehashes just the paths of the entries it changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateTree {
+    /// Hashes of the non-empty nodes, by depth and the leading bits of the path to them
+    nodes: BTreeMap<(usize, Hash), Hash>,
+}
+
+/// Hashes of the siblings along the path to a leaf, leaving out those of empty subtrees.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Proof {
+    /// By the depth of the sibling, from 1 below the root to [`DEPTH`]
+    pub siblings: BTreeMap<usize, Hash>,
+}
+
+impl StateTree {
+    /// Computes the tree of the world from scratch.
+    pub fn new(world: &World) -> Self {
+        let mut tree = Self {
+            nodes: BTreeMap::new(),
+        };
+        let entries = world
+            .account
+            .iter()
+            .map(|(k, v)| (NodeKey::Account(k.clone()), NodeValue::Account(v.clone())))
+            .chain(world.asset_definition.iter().map(|(k, v)| {
+                (
+                    NodeKey::AssetDefinition(k.clone()),
+                    NodeValue::AssetDefinition(v.clone()),
+                )
+            }))
+            .chain(world.account_asset.iter().map(|(k, v)| {
+                (
+                    NodeKey::AccountAsset(k.clone()),
+                    NodeValue::AccountAsset(v.clone()),
+                )
+            }))
+            .chain(world.permission.iter().map(|(k, v)| {
+                (
+                    NodeKey::Permission(k.clone()),
+                    NodeValue::Permission(v.clone()),
+                )
+            }))
+            .chain(world.account_permission.iter().map(|(k, v)| {
+                (
+                    NodeKey::AccountPermission(k.clone()),
+                    NodeValue::AccountPermission(v.clone()),
+                )
+            }))
+            .chain(world.executable.iter().map(|(k, v)| {
+                (
+                    NodeKey::Executable(k.clone()),
+                    NodeValue::Executable(v.hash),
+                )
+            }));
+        for (key, value) in entries {
+            tree.update(&key, Some(&value));
+        }
+
+        tree
+    }
+
+    /// Rehashes the paths of the entries the diff changes.
+    pub fn apply(&mut self, diff: &StateDiff) {
+        for (key, value) in &diff.0 {
+            let value = value.clone().map(NodeValue::<Archive>::from);
+            self.update(key, value.as_ref());
+        }
+    }
+
+    pub fn root(&self) -> Hash {
+        self.node(0, &[0; 32])
+    }
+
+    /// Proves the entry at `key`, or its absence, against [`StateTree::root`].
+    pub fn prove(&self, key: &NodeKey) -> Proof {
+        let path = path(key);
+        let siblings = (1..=DEPTH)
+            .filter_map(|depth| {
+                let sibling = sibling(&path, depth);
+                self.nodes.get(&(depth, sibling)).map(|&hash| (depth, hash))
+            })
+            .collect();
+
+        Proof { siblings }
+    }
+
+    fn update(&mut self, key: &NodeKey, value: Option<&NodeValue<Archive>>) {
+        let path = path(key);
+        let mut hash = value.map_or(EMPTY[DEPTH], |value| leaf(key, value));
+        self.set(DEPTH, prefix(&path, DEPTH), hash);
+        for depth in (0..DEPTH).rev() {
+            let sibling = self.node(depth + 1, &sibling(&path, depth + 1));
+            hash = match bit(&path, depth) {
+                false => branch(&hash, &sibling),
+                true => branch(&sibling, &hash),
+            };
+            self.set(depth, prefix(&path, depth), hash);
+        }
+    }
+
+    fn node(&self, depth: usize, prefix: &Hash) -> Hash {
+        self.nodes
+            .get(&(depth, *prefix))
+            .copied()
+            .unwrap_or(EMPTY[depth])
+    }
+
+    fn set(&mut self, depth: usize, prefix: Hash, hash: Hash) {
+        if hash == EMPTY[depth] {
+            self.nodes.remove(&(depth, prefix));
+        } else {
+            self.nodes.insert((depth, prefix), hash);
+        }
+    }
+}
+
+impl Proof {
+    /// Checks that `key` holds `value` under `root`, or nothing if `value` is `None`.
+    pub fn verify(&self, root: &Hash, key: &NodeKey, value: Option<&NodeValue<Archive>>) -> bool {
+        let path = path(key);
+        let mut hash = value.map_or(EMPTY[DEPTH], |value| leaf(key, value));
+        for depth in (0..DEPTH).rev() {
+            let sibling = self.siblings.get(&(depth + 1)).unwrap_or(&EMPTY[depth + 1]);
+            hash = match bit(&path, depth) {
+                false => branch(&hash, sibling),
+                true => branch(sibling, &hash),
+            };
+        }
+
+        hash == *root
+    }
+}
+
+fn path(key: &NodeKey) -> Hash {
+    Sha256::digest(serde_json::to_vec(key).expect("key should serialize")).into()
+}
+
+fn leaf(key: &NodeKey, value: &NodeValue<Archive>) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([0x00]);
+    hasher.update(serde_json::to_vec(key).expect("key should serialize"));
+    hasher.update(serde_json::to_vec(value).expect("value should serialize"));
+    hasher.finalize().into()
+}
+
+fn branch(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([0x01]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// The bit of the path that chooses between the children of the node at `depth`.
+fn bit(path: &Hash, depth: usize) -> bool {
+    path[depth / 8] & (0x80 >> (depth % 8)) != 0
+}
+
+/// The first `depth` bits of the path, which identify the node at that depth.
+fn prefix(path: &Hash, depth: usize) -> Hash {
+    let mut prefix = [0; 32];
+    let bytes = depth / 8;
+    prefix[..bytes].copy_from_slice(&path[..bytes]);
+    if !depth.is_multiple_of(8) {
+        prefix[bytes] = path[bytes] & !(0xff >> (depth % 8));
+    }
+    prefix
+}
+
+/// The prefix of the sibling of the node at `depth` along the path.
+fn sibling(path: &Hash, depth: usize) -> Hash {
+    let mut sibling = prefix(path, depth);
+    let last = depth - 1;
+    sibling[last / 8] ^= 0x80 >> (last % 8);
+    sibling
+}
@@ -0,0 +1,253 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use derive_more::{Display, Error};
+use serde::{Deserialize, Serialize};
+
+use crate::instruction::{InstructionError, validate_authorizer, validate_instruction};
+use crate::prelude::*;
+use crate::state::{AUTHORIZER, World};
+
+/// Initial state of a world, declared in a JSON file.
+///
+/// Component paths are relative to the file. Permissions list their allowances as `[key, value]`
+/// pairs, and every balance is a mantissa at the scale of its asset:
+///
+/// ```json
+/// {
+///   "authorizer": "authorizer.wasm",
+///   "executables": { "supply_all": "instruction.wasm" },
+///   "accounts": ["alice", "bob"],
+///   "assets": {
+///     "rose": { "owner": "alice", "scale": 0, "balances": { "alice": 500, "bob": 100 } }
+///   },
+///   "permissions": {
+///     "everyman": [
+///       [{ "AccountAsset": ["This", null] }, { "AccountAsset": { "bit_mask": 7 } }]
+///     ]
+///   },
+///   "roles": { "alice": ["everyman"], "bob": ["everyman"] }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Genesis {
+    #[serde(default)]
+    pub context: Context,
+    /// Component implementing the `authorizer` world
+    pub authorizer: PathBuf,
+    /// Components implementing the `instruction` world
+    #[serde(default)]
+    pub executables: BTreeMap<KeyElem, PathBuf>,
+    #[serde(default)]
+    pub accounts: BTreeSet<KeyElem>,
+    #[serde(default)]
+    pub assets: BTreeMap<KeyElem, GenesisAsset>,
+    #[serde(default)]
+    pub permissions: BTreeMap<KeyElem, AllowSet>,
+    /// Permissions granted to each account
+    #[serde(default)]
+    pub roles: BTreeMap<KeyElem, BTreeSet<KeyElem>>,
+}
+
+/// An asset definition along with the balances that make up its total supply.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GenesisAsset {
+    pub owner: KeyElem,
+    /// Decimal places every amount of the asset is kept at
+    pub scale: u8,
+    #[serde(default)]
+    pub balances: BTreeMap<KeyElem, u128>,
+}
+
+/// Reasons a genesis cannot produce a world.
+#[derive(Debug, Display, Error)]
+pub enum GenesisError {
+    /// A file could not be read.
+    #[display("cannot read {}: {source}", path.display())]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// The genesis file is not valid JSON of the expected shape.
+    #[display("invalid genesis: {_0}")]
+    Parse(serde_json::Error),
+    /// A component could not be compiled.
+    #[display("cannot compile {}: {error:#}", path.display())]
+    Compile {
+        path: PathBuf,
+        #[error(not(source))]
+        error: wasmtime::Error,
+    },
+    /// A component does not implement the world it is declared for.
+    #[display("{} does not fit its role: {source}", path.display())]
+    Component {
+        path: PathBuf,
+        source: Box<InstructionError>,
+    },
+    /// An executable is declared under the key reserved for the authorizer.
+    #[display("executable {_0:?} is reserved for the authorizer")]
+    ReservedExecutable(#[error(not(source))] ExecutableK),
+    /// An owner, holder or grantee is not among the accounts.
+    #[display("{_0:?} is not among the accounts")]
+    NoAccount(#[error(not(source))] AccountK),
+    /// A role refers to a permission that is not defined.
+    #[display("{_0:?} is not among the permissions")]
+    NoPermission(#[error(not(source))] PermissionK),
+    /// A permission allows a node type other than that of the key it allows it on.
+    #[display("{permission:?} allows another node type than that of {key:?}")]
+    MismatchedAllowance {
+        permission: PermissionK,
+        key: FlexFuzzyNodeKey,
+    },
+    /// A permission sets bits that stand for no event of the node type.
+    #[display("{permission:?} sets undefined bits {bits:#010b} on {key:?}")]
+    UndefinedBits {
+        permission: PermissionK,
+        key: FlexFuzzyNodeKey,
+        bits: u8,
+    },
+    /// The balances of an asset add up beyond what a numeric holds.
+    #[display("total supply of {_0:?} overflows")]
+    SupplyOverflow(#[error(not(source))] AssetDefinitionK),
+}
+
+impl Genesis {
+    /// Reads the genesis file and builds its world, compiling components on `engine`.
+    pub fn load(path: impl AsRef<Path>, engine: &wasmtime::Engine) -> Result<World, GenesisError> {
+        let path = path.as_ref();
+        let json = std::fs::read(path).map_err(|source| GenesisError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let genesis: Self = serde_json::from_slice(&json).map_err(GenesisError::Parse)?;
+
+        genesis.build(path.parent().unwrap_or(Path::new("")), engine)
+    }
+
+    /// Validates the genesis and builds its world, resolving component paths against `base`.
+    pub fn build(self, base: &Path, engine: &wasmtime::Engine) -> Result<World, GenesisError> {
+        let Genesis {
+            context,
+            authorizer,
+            executables,
+            accounts,
+            assets,
+            permissions,
+            roles,
+        } = self;
+        for (name, permission) in &permissions {
+            for (key, value) in &permission.0 {
+                let permission = SingleKey(name.clone());
+                let key = key.clone();
+                match undefined_bits(&key, value) {
+                    None => return Err(GenesisError::MismatchedAllowance { permission, key }),
+                    Some(0) => {}
+                    Some(bits) => {
+                        return Err(GenesisError::UndefinedBits {
+                            permission,
+                            key,
+                            bits,
+                        });
+                    }
+                }
+            }
+        }
+        let account = |name: &KeyElem| {
+            let account = SingleKey(name.clone());
+            match accounts.contains(name) {
+                true => Ok(account),
+                false => Err(GenesisError::NoAccount(account)),
+            }
+        };
+
+        let mut executable = BTreeMap::new();
+        let authorizer = compile(&base.join(authorizer), engine, validate_authorizer)?;
+        executable.insert(SingleKey(AUTHORIZER.into()), authorizer);
+        for (name, path) in executables {
+            let key = SingleKey(name);
+            if key.0 == AUTHORIZER {
+                return Err(GenesisError::ReservedExecutable(key));
+            }
+            let compiled = compile(&base.join(path), engine, validate_instruction)?;
+            executable.insert(key, compiled);
+        }
+
+        let mut asset_definition = BTreeMap::new();
+        let mut account_asset = BTreeMap::new();
+        for (name, asset) in assets {
+            let key = SingleKey(name.clone());
+            let mut total_supply = Numeric::new(0, asset.scale);
+            for (holder, balance) in asset.balances {
+                let balance = Numeric::new(balance, asset.scale);
+                total_supply = total_supply
+                    .checked_add(balance)
+                    .ok_or_else(|| GenesisError::SupplyOverflow(key.clone()))?;
+                let holder = account(&holder)?;
+                account_asset.insert(
+                    CompositeKey(holder.0, name.clone()),
+                    AccountAssetV { balance },
+                );
+            }
+            let definition = AssetDefinitionV {
+                owner: account(&asset.owner)?,
+                scale: asset.scale,
+                total_supply,
+            };
+            asset_definition.insert(key, definition);
+        }
+
+        let mut account_permission = BTreeMap::new();
+        for (grantee, granted) in roles {
+            let grantee = account(&grantee)?;
+            for permission in granted {
+                if !permissions.contains_key(&permission) {
+                    return Err(GenesisError::NoPermission(SingleKey(permission)));
+                }
+                account_permission.insert(
+                    CompositeKey(grantee.0.clone(), permission),
+                    AccountPermissionV,
+                );
+            }
+        }
+
+        Ok(World {
+            context,
+            executable,
+            permission: permissions
+                .into_iter()
+                .map(|(name, permission)| (SingleKey(name), PermissionV { permission }))
+                .collect(),
+            account: accounts
+                .into_iter()
+                .map(|name| (SingleKey(name), AccountV))
+                .collect(),
+            asset_definition,
+            account_asset,
+            account_permission,
+        })
+    }
+}
+
+/// Compiles the component at `path` and checks it against the world it is declared for.
+fn compile(
+    path: &Path,
+    engine: &wasmtime::Engine,
+    validate: fn(&crate::instruction::WasmComponent) -> Result<(), InstructionError>,
+) -> Result<ExecutableV, GenesisError> {
+    let bytes = std::fs::read(path).map_err(|source| GenesisError::Read {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let executable = ExecutableV::new(engine, &bytes).map_err(|error| GenesisError::Compile {
+        path: path.to_path_buf(),
+        error,
+    })?;
+    validate(&executable.component).map_err(|source| GenesisError::Component {
+        path: path.to_path_buf(),
+        source: Box::new(source),
+    })?;
+
+    Ok(executable)
+}
@@ -1,154 +1,968 @@
-#![expect(dead_code)]
-
 use crate::bindings;
 use crate::prelude as host;
-use crate::prelude::ExecutableK;
+use crate::prelude::{Capture, ExecutableK, KeyElem};
+
+use std::collections::BTreeMap;
 
+use derive_more::{Display, Error};
+use serde::{Deserialize, Serialize};
 use wasmtime_wasi::p2;
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum InstructionEnum {
-    Builtin(BuiltinInstruction),
+    Builtin(Builtin),
     Wasm(WasmInstruction),
 }
 
-pub enum BuiltinInstruction {}
+impl From<BuiltinInstruction> for InstructionEnum {
+    fn from(instruction: BuiltinInstruction) -> Self {
+        Self::Builtin(instruction.into())
+    }
+}
+
+impl From<BuiltinInstruction> for Builtin {
+    /// Leaves the authorizer to its default limits.
+    fn from(instruction: BuiltinInstruction) -> Self {
+        Self {
+            instruction,
+            authorizer: Limits::default(),
+        }
+    }
+}
+
+/// A builtin instruction along with the limits of the authorizer that judges it.
+///
+/// Encodes as the bare instruction while the limits are the default, and as an
+/// `[instruction, limits]` pair otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Builtin {
+    pub instruction: BuiltinInstruction,
+    pub authorizer: Limits,
+}
+
+impl Serialize for Builtin {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.authorizer == Limits::default() {
+            return self.instruction.serialize(serializer);
+        }
+        (&self.instruction, &self.authorizer).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Builtin {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = Builtin;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a builtin instruction, alone or paired with limits")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(self, map: A) -> Result<Builtin, A::Error> {
+                let instruction = BuiltinInstruction::deserialize(
+                    serde::de::value::MapAccessDeserializer::new(map),
+                )?;
+                Ok(instruction.into())
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<Builtin, A::Error> {
+                use serde::de::Error;
+
+                let instruction = seq
+                    .next_element()?
+                    .ok_or_else(|| A::Error::invalid_length(0, &self))?;
+                let authorizer = seq
+                    .next_element()?
+                    .ok_or_else(|| A::Error::invalid_length(1, &self))?;
+                Ok(Builtin {
+                    instruction,
+                    authorizer,
+                })
+            }
+        }
 
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+/// Instructions implemented natively in the host, authorized the same way as Wasm instructions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BuiltinInstruction {
+    /// Registers a new account.
+    RegisterAccount { account: host::AccountK },
+    /// Unregisters an account that holds no assets.
+    UnregisterAccount { account: host::AccountK },
+    /// Defines a new asset owned by the authority, with no supply.
+    RegisterAssetDefinition {
+        asset: host::AssetDefinitionK,
+        /// Decimal places every amount of the asset is kept at
+        scale: u8,
+    },
+    /// Removes the definition of an asset that has no supply left.
+    UnregisterAssetDefinition { asset: host::AssetDefinitionK },
+    /// Sends `amount` of `asset` from one account to another.
+    Transfer {
+        from: host::AccountK,
+        to: host::AccountK,
+        asset: KeyElem,
+        amount: host::Numeric,
+    },
+    /// Mints `amount` of `asset` into the account.
+    Mint {
+        account: host::AccountK,
+        asset: KeyElem,
+        amount: host::Numeric,
+    },
+    /// Burns `amount` of `asset` from the account.
+    Burn {
+        account: host::AccountK,
+        asset: KeyElem,
+        amount: host::Numeric,
+    },
+    /// Defines a new permission granting `allow` to its holders.
+    DefinePermission {
+        permission: host::PermissionK,
+        allow: host::AllowSet,
+    },
+    /// Replaces the allowances of a defined permission, effective for every holder.
+    RedefinePermission {
+        permission: host::PermissionK,
+        allow: host::AllowSet,
+    },
+    /// Deletes a permission that is no longer granted to anyone.
+    DeletePermission { permission: host::PermissionK },
+    /// Grants a defined permission to the account.
+    GrantPermission {
+        account: host::AccountK,
+        permission: KeyElem,
+    },
+    /// Revokes a permission from the account.
+    RevokePermission {
+        account: host::AccountK,
+        permission: KeyElem,
+    },
+    /// Registers the component bytes as a new executable.
+    RegisterExecutable {
+        executable: ExecutableK,
+        bytes: Vec<u8>,
+    },
+    /// Replaces the component of a registered executable.
+    UpgradeExecutable {
+        executable: ExecutableK,
+        bytes: Vec<u8>,
+    },
+    /// Unregisters an executable.
+    UnregisterExecutable { executable: ExecutableK },
+    /// Replaces the authorizer with the component bytes, migrating permissions in the same write.
+    ///
+    /// The current authorizer approves both the swap and the migration. Instructions that have
+    /// already been initiated finish under the authorizer they started with.
+    UpgradeAuthorizer {
+        bytes: Vec<u8>,
+        /// Permissions to rewrite to fit the new authorizer
+        migration: BTreeMap<host::PermissionK, host::PermissionW>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct WasmInstruction {
     pub executable: ExecutableK,
     pub args: String,
+    pub read_policy: ReadPolicy,
+    pub budget: Budget,
+}
+
+/// How the authorizer's verdict on the read request is applied.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReadPolicy {
+    /// Rejects the whole request if any entry is not allowed.
+    #[default]
+    Strict,
+    /// Leaves out of the view whatever is not allowed, so the instruction writes on what remains.
+    Redact,
 }
 
 pub type WasmComponent = wasmtime::component::Component;
 
+/// Execution limits for the instruction and the authorizer, metered separately.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Budget {
+    pub instruction: Limits,
+    pub authorizer: Limits,
+}
+
+/// Execution limits for one component across all of its calls.
+///
+/// Fuel requires an engine configured with `consume_fuel(true)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Limits {
+    /// Fuel shared by every call into the component
+    pub fuel: u64,
+    /// Maximum size in bytes of each linear memory
+    pub memory: usize,
+    /// Maximum number of elements in each table
+    pub table_elements: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            fuel: 1_000_000_000,
+            memory: 64 << 20,
+            table_elements: 10_000,
+        }
+    }
+}
+
+/// The resource a guest ran out of.
+#[derive(Debug, Display, Error, Clone, Copy, PartialEq, Eq)]
+pub enum Exhausted {
+    #[display("fuel")]
+    Fuel,
+    #[display("memory")]
+    Memory,
+    #[display("table")]
+    Table,
+}
+
+impl Exhausted {
+    fn of(error: &wasmtime::Error) -> Option<Self> {
+        if let Some(wasmtime::Trap::OutOfFuel) = error.downcast_ref() {
+            return Some(Self::Fuel);
+        }
+        error.downcast_ref().copied()
+    }
+}
+
+/// Enforces the [`Limits`] of the component running in the store.
+pub struct Limiter {
+    limits: Limits,
+    /// Largest linear memory the component has grown to
+    peak: usize,
+}
+
+impl wasmtime::ResourceLimiter for Limiter {
+    fn memory_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> wasmtime::Result<bool> {
+        if self.limits.memory < desired {
+            return Err(Exhausted::Memory.into());
+        }
+        self.peak = self.peak.max(desired);
+        Ok(true)
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> wasmtime::Result<bool> {
+        if self.limits.table_elements < desired {
+            return Err(Exhausted::Table.into());
+        }
+        Ok(true)
+    }
+}
+
+/// Resources one component consumed across all of its calls.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Usage {
+    pub fuel: u64,
+    /// Peak size in bytes of any linear memory
+    pub memory: usize,
+}
+
+/// Resources consumed by the instruction and the authorizer, metered separately like [`Budget`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Spent {
+    pub instruction: Usage,
+    pub authorizer: Usage,
+}
+
+/// Reads what the component in the store has consumed so far.
+fn usage<T>(store: &wasmtime::Store<ComponentState<T>>) -> Usage {
+    let limiter = &store.data().limiter;
+    Usage {
+        fuel: store
+            .get_fuel()
+            .map_or(0, |left| limiter.limits.fuel.saturating_sub(left)),
+        memory: limiter.peak,
+    }
+}
+
+/// Reasons an instruction can fail anywhere along the state transition.
+#[derive(Debug, Display, Error)]
+pub enum InstructionError {
+    /// The executable is not registered in the world state.
+    #[display("executable not found: {_0:?}")]
+    ExecutableNotFound(#[error(not(source))] ExecutableK),
+    /// The component could not be linked or instantiated.
+    #[display("instantiation failed: {_0:#}")]
+    Instantiation(#[error(not(source))] wasmtime::Error),
+    /// The component ran out of its budget.
+    #[display("{_0} budget exhausted")]
+    BudgetExhausted(#[error(not(source))] Exhausted),
+    /// The guest trapped during a call.
+    #[display("guest trapped: {message}")]
+    GuestTrap {
+        message: String,
+        #[error(not(backtrace))]
+        backtrace: Option<String>,
+    },
+    /// The authorizer rejected the read request on the listed entries.
+    #[display("read request rejected: {_0:?}")]
+    ReadRejected(#[error(not(source))] Vec<host::ReadRejection>),
+    /// The authorizer rejected the write request on the listed entries.
+    #[display("write request rejected: {_0:?}")]
+    WriteRejected(#[error(not(source))] Vec<host::WriteRejection>),
+    /// The guest returned data the host cannot accept.
+    #[display("invalid guest data: {_0}")]
+    InvalidGuestData(#[error(not(source))] String),
+    /// A transfer names the same account as sender and recipient.
+    #[display("cannot transfer from {_0:?} to itself")]
+    SelfTransfer(#[error(not(source))] host::AccountK),
+    /// The write request cannot be applied to the world state.
+    #[display("state violation: {_0}")]
+    StateViolation(crate::state::WriteError),
+    /// The storage could not make the write durable.
+    #[display("storage failed: {_0}")]
+    Storage(std::io::Error),
+}
+
+impl InstructionError {
+    fn instantiation(context: &'static str) -> impl FnOnce(wasmtime::Error) -> Self {
+        move |error| match Exhausted::of(&error) {
+            Some(exhausted) => Self::BudgetExhausted(exhausted),
+            None => Self::Instantiation(error.context(context)),
+        }
+    }
+
+    fn trap(error: wasmtime::Error) -> Self {
+        if let Some(exhausted) = Exhausted::of(&error) {
+            return Self::BudgetExhausted(exhausted);
+        }
+        let backtrace = error
+            .downcast_ref::<wasmtime::WasmBacktrace>()
+            .map(ToString::to_string);
+        Self::GuestTrap {
+            message: error.root_cause().to_string(),
+            backtrace,
+        }
+    }
+}
+
 pub struct Wasmtime {
-    instruction: bindings::Universe,
-    authorizer: bindings::Universe,
-    store: wasmtime::Store<InstructionState>,
+    instruction: Requester,
+    authorizer: bindings::Authorizer,
+    authorizer_store: wasmtime::Store<AuthorizerState>,
+    /// Block context passed to every guest call
+    context: bindings::Context,
+    read_policy: ReadPolicy,
+}
+
+/// The side of an instruction that requests reads and writes.
+enum Requester {
+    Builtin(BuiltinInstruction),
+    Wasm {
+        instance: bindings::Instruction,
+        store: wasmtime::Store<InstructionState>,
+    },
+}
+
+impl Requester {
+    fn read_request(
+        &mut self,
+        context: bindings::Context,
+        authority: &bindings::SingleKey,
+    ) -> Result<bindings::ReadSet, InstructionError> {
+        match self {
+            Requester::Builtin(builtin) => Ok(builtin.read_request()),
+            Requester::Wasm { instance, store } => {
+                let args = store.data().host.args.clone();
+                instance
+                    .call_read_request(store, context, authority, &args)
+                    .map_err(InstructionError::trap)
+            }
+        }
+    }
+
+    fn write_request(
+        &mut self,
+        context: bindings::Context,
+        authority: &bindings::SingleKey,
+        view: &bindings::ViewSet,
+    ) -> Result<bindings::WriteSet, InstructionError> {
+        match self {
+            Requester::Builtin(builtin) => Ok(builtin.write_request()),
+            Requester::Wasm { instance, store } => {
+                let args = store.data().host.args.clone();
+                instance
+                    .call_write_request(store, context, authority, view, &args)
+                    .map_err(InstructionError::trap)
+            }
+        }
+    }
+
+    /// Builtins run natively and consume nothing metered.
+    fn usage(&self) -> Usage {
+        match self {
+            Requester::Builtin(_) => Usage::default(),
+            Requester::Wasm { store, .. } => usage(store),
+        }
+    }
 }
 
-/// Data relevant only during Wasm execution.
-pub struct InstructionState {
-    pub host: HostState,
+/// Data relevant only during Wasm execution, private to the store of one component.
+pub struct ComponentState<T> {
+    pub host: T,
     pub wasi: p2::WasiCtx,
     pub resource_table: wasmtime_wasi::ResourceTable,
+    pub limiter: Limiter,
 }
 
+/// Store data of the untrusted instruction component.
+pub type InstructionState = ComponentState<HostState>;
+
+/// Store data of the authorizer, which shares nothing with the instruction.
+pub type AuthorizerState = ComponentState<()>;
+
 pub struct HostState {
     args: String,
 }
 
-impl p2::IoView for InstructionState {
+impl<T: Send> p2::IoView for ComponentState<T> {
     fn table(&mut self) -> &mut wasmtime_wasi::ResourceTable {
         &mut self.resource_table
     }
 }
-impl p2::WasiView for InstructionState {
+impl<T: Send> p2::WasiView for ComponentState<T> {
     fn ctx(&mut self) -> &mut p2::WasiCtx {
         &mut self.wasi
     }
 }
 
-impl bindings::poc::wit::types::Host for InstructionState {}
+impl<T> bindings::poc::wit::types::Host for ComponentState<T> {}
+
+// --- Builtin intents ---
+
+impl BuiltinInstruction {
+    /// Builtins decide without looking at the world state, so they request no reads.
+    fn read_request(&self) -> bindings::ReadSet {
+        bindings::ReadSet { inner: vec![] }
+    }
+
+    fn write_request(&self) -> bindings::WriteSet {
+        use bindings::poc::wit::types::{
+            AccountAssetW, AccountPermissionW, AccountW, AssetDefinitionW, ExecutableW,
+            FlexCompositeKey, FlexKeyElem, FlexNodeKey, FlexSingleKey, NodeValueWrite, PermissionW,
+            WriteEntry,
+        };
+
+        let account_entry = |account: &host::AccountK, value| WriteEntry {
+            key: FlexNodeKey::Account(FlexSingleKey {
+                e0: FlexKeyElem::That(account.0.clone()),
+            }),
+            value: NodeValueWrite::Account(value),
+        };
+        let asset_entry = |asset: &host::AssetDefinitionK, value| WriteEntry {
+            key: FlexNodeKey::AssetDefinition(asset.clone().into()),
+            value: NodeValueWrite::AssetDefinition(value),
+        };
+        let entry = |account: &host::AccountK, asset: &KeyElem, value| WriteEntry {
+            key: FlexNodeKey::AccountAsset(FlexCompositeKey {
+                e0: FlexKeyElem::That(account.0.clone()),
+                e1: asset.clone(),
+            }),
+            value: NodeValueWrite::AccountAsset(value),
+        };
+        let permission_entry = |permission: &host::PermissionK, value| WriteEntry {
+            key: FlexNodeKey::Permission(permission.clone().into()),
+            value: NodeValueWrite::Permission(value),
+        };
+        let grant_entry = |account: &host::AccountK, permission: &KeyElem, value| WriteEntry {
+            key: FlexNodeKey::AccountPermission(FlexCompositeKey {
+                e0: FlexKeyElem::That(account.0.clone()),
+                e1: permission.clone(),
+            }),
+            value: NodeValueWrite::AccountPermission(value),
+        };
+        let executable_entry = |executable: &ExecutableK, value| WriteEntry {
+            key: FlexNodeKey::Executable(executable.clone().into()),
+            value: NodeValueWrite::Executable(value),
+        };
+        let inner = match self {
+            BuiltinInstruction::RegisterAccount { account } => {
+                vec![account_entry(account, AccountW::Register)]
+            }
+            BuiltinInstruction::UnregisterAccount { account } => {
+                vec![account_entry(account, AccountW::Unregister)]
+            }
+            BuiltinInstruction::RegisterAssetDefinition { asset, scale } => {
+                vec![asset_entry(asset, AssetDefinitionW::Register(*scale))]
+            }
+            BuiltinInstruction::UnregisterAssetDefinition { asset } => {
+                vec![asset_entry(asset, AssetDefinitionW::Unregister)]
+            }
+            BuiltinInstruction::Transfer {
+                from,
+                to,
+                asset,
+                amount,
+            } => vec![
+                entry(from, asset, AccountAssetW::Send((*amount).into())),
+                entry(to, asset, AccountAssetW::Receive((*amount).into())),
+            ],
+            BuiltinInstruction::Mint {
+                account,
+                asset,
+                amount,
+            } => vec![entry(account, asset, AccountAssetW::Mint((*amount).into()))],
+            BuiltinInstruction::Burn {
+                account,
+                asset,
+                amount,
+            } => vec![entry(account, asset, AccountAssetW::Burn((*amount).into()))],
+            BuiltinInstruction::DefinePermission { permission, allow } => {
+                let definition = host::PermissionV {
+                    permission: allow.clone(),
+                };
+                vec![permission_entry(
+                    permission,
+                    PermissionW::Define(definition.into()),
+                )]
+            }
+            BuiltinInstruction::RedefinePermission { permission, allow } => {
+                let definition = host::PermissionV {
+                    permission: allow.clone(),
+                };
+                vec![permission_entry(
+                    permission,
+                    PermissionW::Redefine(definition.into()),
+                )]
+            }
+            BuiltinInstruction::DeletePermission { permission } => {
+                vec![permission_entry(permission, PermissionW::Delete)]
+            }
+            BuiltinInstruction::GrantPermission {
+                account,
+                permission,
+            } => vec![grant_entry(account, permission, AccountPermissionW::Grant)],
+            BuiltinInstruction::RevokePermission {
+                account,
+                permission,
+            } => vec![grant_entry(account, permission, AccountPermissionW::Revoke)],
+            BuiltinInstruction::RegisterExecutable { executable, bytes } => {
+                vec![executable_entry(
+                    executable,
+                    ExecutableW::Register(bytes.clone()),
+                )]
+            }
+            BuiltinInstruction::UpgradeExecutable { executable, bytes } => {
+                vec![executable_entry(
+                    executable,
+                    ExecutableW::Upgrade(bytes.clone()),
+                )]
+            }
+            BuiltinInstruction::UnregisterExecutable { executable } => {
+                vec![executable_entry(executable, ExecutableW::Unregister)]
+            }
+            BuiltinInstruction::UpgradeAuthorizer { bytes, migration } => {
+                let authorizer = host::SingleKey(crate::state::AUTHORIZER.into());
+                let swap = executable_entry(&authorizer, ExecutableW::Upgrade(bytes.clone()));
+                let migration = migration.iter().map(|(permission, write)| {
+                    let write = match write {
+                        host::PermissionW::Define(definition) => {
+                            PermissionW::Define(definition.clone().into())
+                        }
+                        host::PermissionW::Redefine(definition) => {
+                            PermissionW::Redefine(definition.clone().into())
+                        }
+                        host::PermissionW::Delete => PermissionW::Delete,
+                    };
+                    permission_entry(permission, write)
+                });
+                std::iter::once(swap).chain(migration).collect()
+            }
+        };
+
+        bindings::WriteSet { inner }
+    }
+}
 
 // --- State transition ---
 
-impl WasmInstruction {
+/// Links a component against the `instruction` world.
+fn instruction_pre(
+    component: &WasmComponent,
+) -> wasmtime::Result<bindings::InstructionPre<InstructionState>> {
+    let mut linker = wasmtime::component::Linker::new(component.engine());
+    p2::add_to_linker_sync(&mut linker)?;
+    bindings::Instruction::add_to_linker(&mut linker, |state: &mut InstructionState| state)?;
+
+    bindings::InstructionPre::new(linker.instantiate_pre(component)?)
+}
+
+/// Links a component against the `authorizer` world.
+fn authorizer_pre(
+    component: &WasmComponent,
+) -> wasmtime::Result<bindings::AuthorizerPre<AuthorizerState>> {
+    let mut linker = wasmtime::component::Linker::new(component.engine());
+    p2::add_to_linker_sync(&mut linker)?;
+    bindings::Authorizer::add_to_linker(&mut linker, |state: &mut AuthorizerState| state)?;
+
+    bindings::AuthorizerPre::new(linker.instantiate_pre(component)?)
+}
+
+/// Checks that the component implements the `instruction` world.
+pub fn validate_instruction(component: &WasmComponent) -> Result<(), InstructionError> {
+    instruction_pre(component)
+        .map(drop)
+        .map_err(InstructionError::instantiation(
+            "component does not match the instruction world",
+        ))
+}
+
+/// Checks that the component implements the `authorizer` world.
+pub fn validate_authorizer(component: &WasmComponent) -> Result<(), InstructionError> {
+    authorizer_pre(component)
+        .map(drop)
+        .map_err(InstructionError::instantiation(
+            "component does not match the authorizer world",
+        ))
+}
+
+/// Creates a store of its own for one component, enforcing `limits`.
+fn store<T: Send + 'static>(
+    engine: &wasmtime::Engine,
+    host: T,
+    limits: Limits,
+) -> Result<wasmtime::Store<ComponentState<T>>, InstructionError> {
+    let mut store = wasmtime::Store::new(
+        engine,
+        ComponentState {
+            host,
+            wasi: p2::WasiCtxBuilder::new().build(),
+            resource_table: wasmtime_wasi::ResourceTable::new(),
+            limiter: Limiter { limits, peak: 0 },
+        },
+    );
+    store.limiter(|state| &mut state.limiter);
+    store
+        .set_fuel(limits.fuel)
+        .map_err(InstructionError::instantiation("failed to set fuel"))?;
+
+    Ok(store)
+}
+
+/// Instantiates the authorizer in a store of its own.
+fn authorizer(
+    world: &impl crate::state::WorldState,
+    limits: Limits,
+) -> Result<(bindings::Authorizer, wasmtime::Store<AuthorizerState>), InstructionError> {
+    let component = &world.authorizer().component;
+    let pre = authorizer_pre(component).map_err(InstructionError::instantiation(
+        "component does not match the authorizer world",
+    ))?;
+    let mut store = store(component.engine(), (), limits)?;
+    let instance = pre
+        .instantiate(&mut store)
+        .map_err(InstructionError::instantiation(
+            "failed to instantiate authorizer component",
+        ))?;
+
+    Ok((instance, store))
+}
+
+impl InstructionEnum {
     pub fn initiate(
         self,
         authority: host::AccountK,
         world: &impl crate::state::WorldState,
-    ) -> Init {
-        let host = HostState { args: self.args };
-        let executable = world
-            .executable(&self.executable)
-            .expect("executable not found in the world state");
-        let engine = executable.component.engine();
-        let mut store = wasmtime::Store::new(
-            engine,
-            InstructionState {
-                host,
-                wasi: p2::WasiCtxBuilder::new().build(),
-                resource_table: wasmtime_wasi::ResourceTable::new(),
-            },
+    ) -> Result<Init, InstructionError> {
+        match self {
+            InstructionEnum::Builtin(builtin) => builtin.initiate(authority, world),
+            InstructionEnum::Wasm(wasm) => wasm.initiate(authority, world),
+        }
+    }
+
+    /// Runs the whole state transition from initiation to write, returning its record.
+    pub fn execute(
+        self,
+        authority: host::AccountK,
+        world: &mut impl crate::state::WorldState,
+    ) -> Result<Record, InstructionError> {
+        let record = self
+            .initiate(authority, world)?
+            .read_request()?
+            .read_approval(world)?
+            .read(world)?
+            .write_request()?
+            .write_approval()?
+            .write(world)?
+            .into_record();
+
+        Ok(record)
+    }
+
+    /// Runs the whole state transition against an overlay of the world, leaving it untouched.
+    ///
+    /// Collects what every step produced up to where the instruction would stop.
+    pub fn simulate(
+        self,
+        authority: host::AccountK,
+        world: &impl crate::state::WorldState,
+    ) -> Simulation {
+        let mut overlay = crate::state::Overlay::new(world);
+        let mut simulation = Simulation {
+            read_request: None,
+            view: None,
+            write_request: None,
+            events: None,
+            read_verdict: None,
+            write_verdict: None,
+            outcome: Ok(crate::state::StateDiff::default()),
+        };
+        let run = || {
+            let mut to_read = self.initiate(authority, &overlay)?.read_request()?;
+            simulation.read_request = Some(to_read.request.clone());
+            let (verdict, permission) = to_read.read_verdict(&overlay)?;
+            simulation.read_verdict = Some(verdict.clone());
+            let has_read = to_read.approve(verdict, permission)?.read(&overlay)?;
+            simulation.view = Some(has_read.result.clone());
+            let mut to_write = has_read.write_request()?;
+            simulation.write_request = Some(to_write.request.clone());
+            simulation.events = Some(host::EventSet::from((
+                to_write.request.clone(),
+                to_write.authority.clone(),
+            )));
+            let verdict = to_write.write_verdict()?;
+            simulation.write_verdict = Some(verdict.clone());
+            to_write.approve(verdict)?.write(&mut overlay)?;
+
+            Ok(())
+        };
+        let outcome = run();
+        simulation.outcome = outcome.map(|()| overlay.into_diff());
+
+        simulation
+    }
+}
+
+/// What an instruction would read, write, and change, as found by [`InstructionEnum::simulate`].
+///
+/// Steps the instruction did not reach are left `None`.
+#[derive(Debug)]
+pub struct Simulation {
+    pub read_request: Option<host::ReadSet>,
+    /// How the authorizer judged the read request, including what a redacting read policy keeps
+    pub read_verdict: Option<host::ReadVerdict>,
+    pub view: Option<host::ViewSet>,
+    pub write_request: Option<host::WriteSet>,
+    /// What the write request requires of the authority
+    pub events: Option<host::EventSet>,
+    /// How the authorizer judged the write request
+    pub write_verdict: Option<host::WriteVerdict>,
+    /// The would-be state diff, or why the instruction would fail, including any rejections
+    pub outcome: Result<crate::state::StateDiff, InstructionError>,
+}
+
+impl Builtin {
+    pub fn initiate(
+        self,
+        authority: host::AccountK,
+        world: &impl crate::state::WorldState,
+    ) -> Result<Init, InstructionError> {
+        // Sending and receiving on the same balance would be two intents on one key
+        if let BuiltinInstruction::Transfer { from, to, .. } = &self.instruction
+            && from == to
+        {
+            return Err(InstructionError::SelfTransfer(from.clone()));
+        }
+        let (authorizer, authorizer_store) = authorizer(world, self.authorizer)?;
+        let record = Record::new(
+            authority.clone(),
+            InstructionEnum::Builtin(self.clone()),
+            None,
+            world,
         );
+        let wasmtime = Wasmtime {
+            instruction: Requester::Builtin(self.instruction),
+            authorizer,
+            authorizer_store,
+            context: world.context().into(),
+            // Builtins read nothing
+            read_policy: ReadPolicy::default(),
+        };
 
-        let mut linker = wasmtime::component::Linker::new(engine);
-        p2::add_to_linker_sync(&mut linker).expect("failed to add WASI bindings to linker");
-        bindings::Universe::add_to_linker(&mut linker, |state: &mut InstructionState| state)
-            .expect("failed to add bindings to linker");
+        Ok(Init {
+            authority,
+            wasmtime,
+            record,
+        })
+    }
+}
 
-        let instruction =
-            bindings::Universe::instantiate(&mut store, &executable.component, &linker)
-                .expect("failed to instantiate instruction component");
-        let authorizer = bindings::Universe::instantiate(&mut store, world.authorizer(), &linker)
-            .expect("failed to instantiate authorizer component");
+impl WasmInstruction {
+    pub fn initiate(
+        self,
+        authority: host::AccountK,
+        world: &impl crate::state::WorldState,
+    ) -> Result<Init, InstructionError> {
+        let instruction = InstructionEnum::Wasm(self.clone());
+        let WasmInstruction {
+            executable,
+            args,
+            read_policy,
+            budget,
+        } = self;
+        let host = HostState { args };
+        let executable = world
+            .executable(&executable)
+            .ok_or(InstructionError::ExecutableNotFound(executable))?;
+        let component = &executable.component;
+        let pre = instruction_pre(component).map_err(InstructionError::instantiation(
+            "component does not match the instruction world",
+        ))?;
+        let mut store = store(component.engine(), host, budget.instruction)?;
+        let instance = pre
+            .instantiate(&mut store)
+            .map_err(InstructionError::instantiation(
+                "failed to instantiate instruction component",
+            ))?;
+        let (authorizer, authorizer_store) = authorizer(world, budget.authorizer)?;
+        let record = Record::new(authority.clone(), instruction, Some(executable.hash), world);
         let wasmtime = Wasmtime {
-            instruction,
+            instruction: Requester::Wasm { instance, store },
             authorizer,
-            store,
+            authorizer_store,
+            context: world.context().into(),
+            read_policy,
         };
 
-        Init {
+        Ok(Init {
             authority,
             wasmtime,
-        }
+            record,
+        })
     }
 }
 
 pub struct Init {
     authority: host::AccountK,
     wasmtime: Wasmtime,
+    record: Record,
 }
 
 impl Init {
-    pub fn read_request(self) -> ToRead {
-        let args = self.wasmtime.store.data().host.args.clone();
+    pub fn read_request(self) -> Result<ToRead, InstructionError> {
         let Init {
             authority,
             mut wasmtime,
+            mut record,
         } = self;
         let request = wasmtime
             .instruction
-            .call_read_request(&mut wasmtime.store, &args)
-            .expect("failed to call read_request function");
+            .read_request(wasmtime.context, &authority.clone().into())?;
+        let request = host::ReadSet::from((request, authority.clone()));
+        record.read_request = request.clone();
 
-        ToRead {
+        Ok(ToRead {
             authority,
             wasmtime,
             request,
-        }
+            record,
+        })
     }
 }
 
 pub struct ToRead {
     authority: host::AccountK,
     wasmtime: Wasmtime,
-    request: bindings::ReadSet,
+    request: host::ReadSet,
+    record: Record,
 }
 
 impl ToRead {
-    pub fn read_approval(self, world: &impl crate::state::WorldState) -> Result<Reading, ()> {
+    pub fn read_approval(
+        mut self,
+        world: &impl crate::state::WorldState,
+    ) -> Result<Reading, InstructionError> {
+        let (verdict, permission) = self.read_verdict(world)?;
+        self.approve(verdict, permission)
+    }
+
+    /// Asks the authorizer to judge the read request, without acting on the verdict.
+    fn read_verdict(
+        &mut self,
+        world: &impl crate::state::WorldState,
+    ) -> Result<(host::ReadVerdict, bindings::AllowSet), InstructionError> {
+        let permission = world.permission(&self.authority);
+        self.record.permission = permission.clone();
+        let permission = bindings::AllowSet::from((permission, self.authority.clone()));
+
+        let verdict = self
+            .wasmtime
+            .authorizer
+            .call_read_approval(
+                &mut self.wasmtime.authorizer_store,
+                self.wasmtime.context,
+                &self.authority.clone().into(),
+                &self.request.clone().into(),
+                &permission,
+            )
+            .map_err(InstructionError::trap)?;
+        let approved = bindings::ReadSet {
+            inner: verdict.approved,
+        };
+        let verdict = host::ReadVerdict {
+            approved: (approved, self.authority.clone()).into(),
+            rejected: verdict
+                .rejected
+                .into_iter()
+                .map(|rejection| (rejection, self.authority.clone()).into())
+                .collect(),
+        };
+
+        Ok((verdict, permission))
+    }
+
+    /// Proceeds to read as much as the verdict allows under the read policy.
+    fn approve(
+        self,
+        verdict: host::ReadVerdict,
+        permission: bindings::AllowSet,
+    ) -> Result<Reading, InstructionError> {
         let ToRead {
             authority,
-            mut wasmtime,
+            wasmtime,
             request,
+            mut record,
         } = self;
-        let permission = world.permission(&authority);
-        let permission = bindings::AllowSet::from((permission, authority.clone()));
-
-        let verdict = wasmtime
-            .authorizer
-            .call_read_approval(&mut wasmtime.store, &request, &permission)
-            .expect("failed to call read_approval function");
-        if !verdict {
-            return Err(());
-        }
+        let approved = match wasmtime.read_policy {
+            ReadPolicy::Strict if !verdict.rejected.is_empty() => {
+                return Err(InstructionError::ReadRejected(verdict.rejected));
+            }
+            ReadPolicy::Strict => None,
+            ReadPolicy::Redact => Some(verdict.approved.clone()),
+        };
+        record.read_verdict = verdict;
 
         Ok(Reading {
             authority,
             wasmtime,
             request,
+            approved,
             permission,
+            record,
         })
     }
 }
@@ -156,27 +970,39 @@ impl ToRead {
 pub struct Reading {
     authority: host::AccountK,
     wasmtime: Wasmtime,
-    request: bindings::ReadSet,
+    request: host::ReadSet,
+    /// What the view is redacted to, if the read policy redacts
+    approved: Option<host::ReadSet>,
     permission: bindings::AllowSet,
+    record: Record,
 }
 
 impl Reading {
-    pub fn read(self, world: &impl crate::state::WorldState) -> Result<HasRead, ()> {
+    pub fn read(self, world: &impl crate::state::WorldState) -> Result<HasRead, InstructionError> {
         let Reading {
             authority,
             wasmtime,
             request,
+            approved,
             permission,
+            mut record,
         } = self;
-        let request = host::ReadSet::from(request);
         println!("Reading request: {:#?}", &request);
-        let result = world.read(&request).into();
+        let mut result = world.read(&request);
+        // Never reveals more than requested, whatever the authorizer approved
+        if let Some(approved) = approved {
+            result
+                .0
+                .retain(|key, _| approved.0.keys().any(|capture| capture.captures(key)));
+        }
+        record.view = result.clone().into();
 
         Ok(HasRead {
             authority,
             wasmtime,
             result,
             permission,
+            record,
         })
     }
 }
@@ -184,81 +1010,137 @@ impl Reading {
 pub struct HasRead {
     authority: host::AccountK,
     wasmtime: Wasmtime,
-    result: bindings::ViewSet,
+    result: host::ViewSet,
     permission: bindings::AllowSet,
+    record: Record,
 }
 
 impl HasRead {
-    pub fn write_request(self) -> ToWrite {
-        let args = self.wasmtime.store.data().host.args.clone();
+    pub fn write_request(self) -> Result<ToWrite, InstructionError> {
         let HasRead {
             authority,
             mut wasmtime,
             result,
             permission,
+            mut record,
         } = self;
-        let request = wasmtime
-            .instruction
-            .call_write_request(&mut wasmtime.store, &result, &args)
-            .expect("failed to call write_request function");
+        let request = wasmtime.instruction.write_request(
+            wasmtime.context,
+            &authority.clone().into(),
+            &result.into(),
+        )?;
+        // The instruction is not called again
+        record.spent.instruction = wasmtime.instruction.usage();
+        let request = host::WriteSet::try_from((request, authority.clone()))?;
+        record.write_request = request.clone();
 
-        ToWrite {
+        Ok(ToWrite {
             authority,
             wasmtime,
             permission,
             request,
-        }
+            record,
+        })
     }
 }
 
 pub struct ToWrite {
     authority: host::AccountK,
     wasmtime: Wasmtime,
-    request: bindings::WriteSet,
+    request: host::WriteSet,
     permission: bindings::AllowSet,
+    record: Record,
 }
 
 impl ToWrite {
-    pub fn write_approval(self) -> Result<Writing, ()> {
+    pub fn write_approval(mut self) -> Result<Writing, InstructionError> {
+        let verdict = self.write_verdict()?;
+        self.approve(verdict)
+    }
+
+    /// Asks the authorizer to judge the write request, without acting on the verdict.
+    fn write_verdict(&mut self) -> Result<host::WriteVerdict, InstructionError> {
+        let events = host::EventSet::from((self.request.clone(), self.authority.clone()));
+        let intent = events.clone().into();
+        self.record.events = events;
+
+        let verdict = self
+            .wasmtime
+            .authorizer
+            .call_write_approval(
+                &mut self.wasmtime.authorizer_store,
+                self.wasmtime.context,
+                &self.authority.clone().into(),
+                &intent,
+                &self.permission,
+            )
+            .map_err(InstructionError::trap)?;
+        // The authorizer is not called again
+        self.record.spent.authorizer = usage(&self.wasmtime.authorizer_store);
+        let rejected = verdict
+            .rejected
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_, _>>()?;
+
+        Ok(host::WriteVerdict { rejected })
+    }
+
+    /// Proceeds to write if the verdict rejects nothing.
+    fn approve(self, verdict: host::WriteVerdict) -> Result<Writing, InstructionError> {
+        if !verdict.rejected.is_empty() {
+            return Err(InstructionError::WriteRejected(verdict.rejected));
+        }
         let ToWrite {
             authority,
-            mut wasmtime,
             request,
-            permission,
+            record,
+            ..
         } = self;
-        let intent = bindings::EventSet::from(&request);
 
-        let verdict = wasmtime
-            .authorizer
-            .call_write_approval(&mut wasmtime.store, &intent, &permission)
-            .expect("failed to call write_approval function");
-        if !verdict {
-            return Err(());
-        }
-
-        Ok(Writing { authority, request })
+        Ok(Writing {
+            authority,
+            request,
+            record,
+        })
     }
 }
 
 pub struct Writing {
     authority: host::AccountK,
-    request: bindings::WriteSet,
+    request: host::WriteSet,
+    record: Record,
 }
 
 impl Writing {
-    pub fn write(self, world: &mut impl crate::state::WorldState) -> Result<HasWritten, ()> {
-        let Writing { authority, request } = self;
-        let request = host::WriteSet::from(request);
+    pub fn write(
+        self,
+        world: &mut impl crate::state::WorldState,
+    ) -> Result<HasWritten, InstructionError> {
+        let Writing {
+            authority,
+            request,
+            mut record,
+        } = self;
         println!("Writing request: {:#?}", &request);
-        world.write(&request, authority.clone());
-        let result = (request, authority).into();
+        let diff = world
+            .stage(&request, &authority)
+            .map_err(InstructionError::StateViolation)?;
+        record.diff = diff.clone().into();
+        world.commit(diff).map_err(InstructionError::Storage)?;
 
-        Ok(HasWritten { result })
+        Ok(HasWritten { record })
     }
 }
 
 pub struct HasWritten {
-    result: bindings::WriteSet,
+    record: Record,
+}
+
+impl HasWritten {
+    pub fn into_record(self) -> Record {
+        self.record
+    }
 }
 
 // pub struct ToPay;
@@ -267,4 +1149,168 @@ pub struct HasWritten {
 
 // pub struct HasPaid;
 
-pub struct Record;
+/// Everything one instruction run went through, to be archived and indexed.
+///
+/// Only runs that complete leave a record, so their write request was approved as a whole.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    pub authority: host::AccountK,
+    /// The instruction as submitted
+    pub instruction: InstructionEnum,
+    /// Hash of the instruction component, or `None` for builtins
+    pub executable: Option<host::Hash>,
+    /// Hash of the authorizer that approved the run
+    pub authorizer: host::Hash,
+    pub context: host::Context,
+    pub read_request: host::ReadSet,
+    /// Allowances of the authority the run was approved against
+    pub permission: host::AllowSet,
+    pub read_verdict: host::ReadVerdict,
+    pub view: host::Tree<host::Archive>,
+    pub write_request: host::WriteSet,
+    pub events: host::EventSet,
+    pub spent: Spent,
+    pub diff: crate::state::ArchivedDiff,
+}
+
+impl Record {
+    /// Starts a record of what is known before the instruction is called.
+    fn new(
+        authority: host::AccountK,
+        instruction: InstructionEnum,
+        executable: Option<host::Hash>,
+        world: &impl crate::state::WorldState,
+    ) -> Self {
+        Self {
+            authority,
+            instruction,
+            executable,
+            authorizer: world.authorizer().hash,
+            context: world.context(),
+            read_request: host::FuzzyTree(BTreeMap::new()),
+            permission: host::FlexFuzzyTree(BTreeMap::new()),
+            read_verdict: host::ReadVerdict {
+                approved: host::FuzzyTree(BTreeMap::new()),
+                rejected: vec![],
+            },
+            view: host::Tree(BTreeMap::new()),
+            write_request: host::FlexTree(BTreeMap::new()),
+            events: host::Tree(BTreeMap::new()),
+            spent: Spent::default(),
+            diff: crate::state::ArchivedDiff::default(),
+        }
+    }
+}
+
+// --- Replay ---
+
+/// Part of a [`Record`] that a replay is checked against, in the order the run produces them.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    #[display("executable")]
+    Executable,
+    #[display("authorizer")]
+    Authorizer,
+    #[display("context")]
+    Context,
+    #[display("read request")]
+    ReadRequest,
+    #[display("permission")]
+    Permission,
+    #[display("read verdict")]
+    ReadVerdict,
+    #[display("view")]
+    View,
+    #[display("write request")]
+    WriteRequest,
+    /// Also where a rejected write request fails the replay, as a recorded run had it approved
+    #[display("events")]
+    Events,
+    #[display("diff")]
+    Diff,
+}
+
+/// Where a replay first departed from its record.
+#[derive(Debug, Display, Error)]
+pub enum Divergence {
+    /// The replay produced something other than what was recorded.
+    #[display("replay differs in {field}")]
+    Mismatch {
+        field: Field,
+        /// The replay up to and including the divergent field
+        replayed: Box<Record>,
+    },
+    /// The replay failed while producing a field where the recorded run succeeded.
+    #[display("replay failed at {field}: {source}")]
+    Failed {
+        field: Field,
+        source: Box<InstructionError>,
+    },
+}
+
+impl Record {
+    /// Re-runs the recorded instruction on a snapshot of the world taken before it ran, leaving the
+    /// snapshot untouched.
+    ///
+    /// Every intermediate set and the final diff are checked as soon as they are produced, so the
+    /// first divergence is reported. Resources spent are not compared, as they may change with the
+    /// engine without changing the outcome.
+    pub fn replay(&self, world: &impl crate::state::WorldState) -> Result<(), Divergence> {
+        let mut overlay = crate::state::Overlay::new(world);
+        let failed = |field| {
+            move |source| Divergence::Failed {
+                field,
+                source: Box::new(source),
+            }
+        };
+
+        let init = self
+            .instruction
+            .clone()
+            .initiate(self.authority.clone(), &overlay)
+            .map_err(failed(Field::Executable))?;
+        self.verify(
+            &init.record,
+            &[Field::Executable, Field::Authorizer, Field::Context],
+        )?;
+        let to_read = init.read_request().map_err(failed(Field::ReadRequest))?;
+        self.verify(&to_read.record, &[Field::ReadRequest])?;
+        let reading = to_read
+            .read_approval(&overlay)
+            .map_err(failed(Field::ReadVerdict))?;
+        self.verify(&reading.record, &[Field::Permission, Field::ReadVerdict])?;
+        let has_read = reading.read(&overlay).map_err(failed(Field::View))?;
+        self.verify(&has_read.record, &[Field::View])?;
+        let to_write = has_read
+            .write_request()
+            .map_err(failed(Field::WriteRequest))?;
+        self.verify(&to_write.record, &[Field::WriteRequest])?;
+        let writing = to_write.write_approval().map_err(failed(Field::Events))?;
+        self.verify(&writing.record, &[Field::Events])?;
+        let has_written = writing.write(&mut overlay).map_err(failed(Field::Diff))?;
+        self.verify(&has_written.record, &[Field::Diff])
+    }
+
+    /// Checks the fields of a replay in order against this record.
+    fn verify(&self, replayed: &Record, fields: &[Field]) -> Result<(), Divergence> {
+        let diverges = |field| match field {
+            Field::Executable => self.executable != replayed.executable,
+            Field::Authorizer => self.authorizer != replayed.authorizer,
+            Field::Context => self.context != replayed.context,
+            Field::ReadRequest => self.read_request != replayed.read_request,
+            Field::Permission => self.permission != replayed.permission,
+            Field::ReadVerdict => self.read_verdict != replayed.read_verdict,
+            Field::View => self.view != replayed.view,
+            Field::WriteRequest => self.write_request != replayed.write_request,
+            Field::Events => self.events != replayed.events,
+            Field::Diff => self.diff != replayed.diff,
+        };
+        match fields.iter().copied().find(|&field| diverges(field)) {
+            Some(field) => Err(Divergence::Mismatch {
+                field,
+                replayed: Box::new(replayed.clone()),
+            }),
+            None => Ok(()),
+        }
+    }
+}
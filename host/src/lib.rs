@@ -1,46 +1,121 @@
 mod bindings;
-mod instruction;
-mod state;
+pub mod commitment;
+pub mod genesis;
+pub mod instruction;
+pub mod state;
+pub mod storage;
+pub mod transaction;
 mod types;
 
 pub mod prelude {
-    pub use super::types::{allow::*, event::*, general::*, read::*, view::*, write::*};
+    pub use super::types::{
+        allow::*, event::*, general::*, read::*, verdict::*, view::*, write::*,
+    };
 }
 
 #[cfg(test)]
 mod tests {
     use prelude::{
-        AccountAssetA, AccountAssetK, AccountAssetV, AccountPermissionK, CompositeKey, ExecutableK,
-        ExecutableV, FlexFuzzyCompositeKey, FlexFuzzyNodeKey, FlexFuzzyTree, FlexKeyElem,
-        NodeValue, PermissionK, PermissionV, SingleKey,
+        AccountA, AccountAssetA, AccountAssetE, AccountAssetK, AccountAssetR, AccountAssetV,
+        AccountK, AccountPermissionA, AccountPermissionK, AccountPermissionV, AccountV,
+        AssetDefinitionA, AssetDefinitionK, AssetDefinitionV, CompositeKey, Context, ExecutableA,
+        ExecutableK, ExecutableV, FlexFuzzyCompositeKey, FlexFuzzyNodeKey, FlexFuzzySingleKey,
+        FlexFuzzyTree, FlexKeyElem, FuzzyCompositeKey, FuzzyNodeKey, FuzzySingleKey, NodeKey,
+        NodeValue, Numeric, PermissionA, PermissionK, PermissionV, ReadRejection, SingleKey,
+        WriteRejection,
     };
 
     use super::*;
     use std::collections::BTreeMap;
     use std::sync::LazyLock;
-    use wasmtime::component;
+
+    /// Runs the scenario on the plain world, then on it stored in memory and on disk, binding
+    /// `$world` to each in turn.
+    macro_rules! on_every_backend {
+        ($fixture:expr, |$world:ident| $scenario:block) => {{
+            // Scenarios that only read leave the world unmutated
+            {
+                #[allow(unused_mut)]
+                let mut $world = $fixture;
+                $scenario
+            }
+            {
+                #[allow(unused_mut)]
+                let mut $world = storage::Stored::new($fixture, storage::Memory);
+                $scenario
+            }
+            let dir = scratch(&format!("backend-{}", line!()));
+            let fixture = $fixture;
+            let disk = storage::Disk::create(&dir, &fixture).expect("should create store");
+            #[allow(unused_mut)]
+            let mut $world = storage::Stored::new(fixture, disk);
+            $scenario
+            drop($world);
+            std::fs::remove_dir_all(dir).expect("should clean up");
+        }};
+    }
+
+    /// An empty directory of its own under the system temporary directory.
+    fn scratch(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("poc5358-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    static ACCOUNT: LazyLock<BTreeMap<AccountK, AccountV>> = LazyLock::new(|| {
+        ["alice", "bob", "carol", "dave", "eve"]
+            .map(|name| (SingleKey(name.into()), AccountV))
+            .into()
+    });
+
+    static ASSET_DEFINITION: LazyLock<BTreeMap<AssetDefinitionK, AssetDefinitionV>> =
+        LazyLock::new(|| {
+            [
+                (
+                    SingleKey("rose".into()),
+                    AssetDefinitionV {
+                        owner: SingleKey("alice".into()),
+                        scale: 0,
+                        total_supply: 780.into(),
+                    },
+                ),
+                (
+                    SingleKey("tulip".into()),
+                    AssetDefinitionV {
+                        owner: SingleKey("eve".into()),
+                        scale: 0,
+                        total_supply: 90.into(),
+                    },
+                ),
+            ]
+            .into()
+        });
 
     static ACCOUNT_ASSET: LazyLock<BTreeMap<AccountAssetK, AccountAssetV>> = LazyLock::new(|| {
         [
             (
                 CompositeKey("alice".into(), "rose".into()),
-                AccountAssetV { balance: 500 },
+                AccountAssetV {
+                    balance: 500.into(),
+                },
             ),
             (
                 CompositeKey("bob".into(), "rose".into()),
-                AccountAssetV { balance: 100 },
+                AccountAssetV {
+                    balance: 100.into(),
+                },
             ),
             (
                 CompositeKey("carol".into(), "rose".into()),
-                AccountAssetV { balance: 90 },
+                AccountAssetV { balance: 90.into() },
             ),
             (
                 CompositeKey("dave".into(), "rose".into()),
-                AccountAssetV { balance: 90 },
+                AccountAssetV { balance: 90.into() },
             ),
             (
                 CompositeKey("eve".into(), "tulip".into()),
-                AccountAssetV { balance: 90 },
+                AccountAssetV { balance: 90.into() },
             ),
         ]
         .into()
@@ -48,87 +123,91 @@ mod tests {
 
     #[test]
     fn instruction_flows() {
-        let mut world = state::World {
-            authorizer: AUTHORIZER.clone(),
-            executable: EXECUTABLE.clone(),
-            permission: PERMISSION.clone(),
-            account_asset: ACCOUNT_ASSET.clone(),
-            account_permission: ACCOUNT_PERMISSION.clone(),
-        };
-        world
-            .account_permission
-            .insert(CompositeKey("alice".into(), "almighty".into()), ());
+        on_every_backend!(with_grant(world(), "alice", "almighty"), |world| {
+            let supply_all = instruction::WasmInstruction {
+                executable: SingleKey("supply_all".to_string()),
+                args: serde_json::json!({
+                    "asset": "rose",
+                    "threshold": 100,
+                    "supply_amount": 50,
+                    "supplier": "alice"
+                })
+                .to_string(),
+                read_policy: instruction::ReadPolicy::default(),
+                budget: instruction::Budget::default(),
+            };
+            let authority = SingleKey("alice".into());
 
-        let supply_all = instruction::WasmInstruction {
-            executable: SingleKey("supply_all".to_string()),
-            args: serde_json::json!({
-                "asset": "rose",
-                "threshold": 100,
-                "supply_amount": 50,
-                "supplier": "alice"
-            })
-            .to_string(),
-        };
-        let authority = SingleKey("alice".into());
-
-        println!("Initiating instruction");
-        supply_all
-            .initiate(authority, &world)
-            .read_request()
-            .read_approval(&world)
-            .expect("read request should be approved")
-            .read(&world)
-            .expect("should read")
-            .write_request()
-            .write_approval()
-            .expect("write request should be approved")
-            .write(&mut world)
-            .expect("should write");
+            println!("Initiating instruction");
+            supply_all
+                .initiate(authority, &world)
+                .expect("should initiate")
+                .read_request()
+                .expect("should request read")
+                .read_approval(&world)
+                .expect("read request should be approved")
+                .read(&world)
+                .expect("should read")
+                .write_request()
+                .expect("should request write")
+                .write_approval()
+                .expect("write request should be approved")
+                .write(&mut world)
+                .expect("should write");
 
-        let expected = [
-            (
-                CompositeKey("alice".into(), "rose".into()),
-                AccountAssetV { balance: 400 },
-            ),
-            (
-                CompositeKey("bob".into(), "rose".into()),
-                AccountAssetV { balance: 100 },
-            ),
-            (
-                CompositeKey("carol".into(), "rose".into()),
-                AccountAssetV { balance: 140 },
-            ),
-            (
-                CompositeKey("dave".into(), "rose".into()),
-                AccountAssetV { balance: 140 },
-            ),
-            (
-                CompositeKey("eve".into(), "tulip".into()),
-                AccountAssetV { balance: 90 },
-            ),
-        ];
+            let expected = [
+                (
+                    CompositeKey("alice".into(), "rose".into()),
+                    AccountAssetV {
+                        balance: 400.into(),
+                    },
+                ),
+                (
+                    CompositeKey("bob".into(), "rose".into()),
+                    AccountAssetV {
+                        balance: 100.into(),
+                    },
+                ),
+                (
+                    CompositeKey("carol".into(), "rose".into()),
+                    AccountAssetV {
+                        balance: 140.into(),
+                    },
+                ),
+                (
+                    CompositeKey("dave".into(), "rose".into()),
+                    AccountAssetV {
+                        balance: 140.into(),
+                    },
+                ),
+                (
+                    CompositeKey("eve".into(), "tulip".into()),
+                    AccountAssetV { balance: 90.into() },
+                ),
+            ];
 
-        assert_eq!(world.account_asset, expected.into());
+            assert_eq!(world.account_asset, expected.into());
+        });
     }
 
-    static ENGINE: LazyLock<wasmtime::Engine> = LazyLock::new(wasmtime::Engine::default);
-
-    static AUTHORIZER: LazyLock<wasmtime::component::Component> = LazyLock::new(|| {
-        component::Component::from_file(
-            &ENGINE,
-            "../target/wasm32-wasip2/debug/authorizer.wasm",
-        )
-        .expect("component should have been built by: cargo build --target wasm32-wasip2 --manifest-path guest/authorizer/Cargo.toml")
+    static ENGINE: LazyLock<wasmtime::Engine> = LazyLock::new(|| {
+        wasmtime::Engine::new(wasmtime::Config::new().consume_fuel(true))
+            .expect("engine should be configured")
     });
 
     static EXECUTABLE: LazyLock<BTreeMap<ExecutableK, ExecutableV>> = LazyLock::new(|| {
-        let component = component::Component::from_file(
-                &ENGINE,
-                "../target/wasm32-wasip2/debug/instruction.wasm",
-            )
-            .expect("component should have been built by: cargo build --target wasm32-wasip2 --manifest-path guest/instruction/Cargo.toml");
-
-        [(SingleKey("supply_all".into()), ExecutableV { component })].into()
+        [
+            ("authorizer", "authorizer"),
+            ("supply_all", "instruction"),
+            ("swap", "swap"),
+        ]
+        .map(|(name, guest)| {
+            let bytes = std::fs::read(format!("../target/wasm32-wasip2/debug/{guest}.wasm"))
+                .unwrap_or_else(|_| panic!("component should have been built by: cargo build --target wasm32-wasip2 --manifest-path guest/{guest}/Cargo.toml"));
+            let executable = ExecutableV::new(&ENGINE, &bytes).expect("component should compile");
+            (SingleKey(name.into()), executable)
+        })
+        .into()
     });
 
     static PERMISSION: LazyLock<BTreeMap<PermissionK, PermissionV>> = LazyLock::new(|| {
@@ -136,14 +215,56 @@ mod tests {
             (
                 SingleKey("almighty".into()),
                 PermissionV {
-                    permission: FlexFuzzyTree(BTreeMap::from([(
-                        // Any (account, asset) pair
-                        FlexFuzzyNodeKey::AccountAsset(FlexFuzzyCompositeKey(None, None)),
-                        NodeValue::AccountAsset(AccountAssetA {
-                            // Can burn, mint, send, receive, and read
-                            bit_mask: 0b0011_0111,
-                        }),
-                    )])),
+                    permission: FlexFuzzyTree(BTreeMap::from([
+                        (
+                            // Any account
+                            FlexFuzzyNodeKey::Account(FlexFuzzySingleKey(None)),
+                            NodeValue::Account(AccountA {
+                                // Can unregister, register, and read
+                                bit_mask: 0b0000_0111,
+                            }),
+                        ),
+                        (
+                            // Any asset definition
+                            FlexFuzzyNodeKey::AssetDefinition(FuzzySingleKey(None)),
+                            NodeValue::AssetDefinition(AssetDefinitionA {
+                                // Can unregister, register, and read
+                                bit_mask: 0b0000_0111,
+                            }),
+                        ),
+                        (
+                            // Any (account, asset) pair
+                            FlexFuzzyNodeKey::AccountAsset(FlexFuzzyCompositeKey(None, None)),
+                            NodeValue::AccountAsset(AccountAssetA {
+                                // Can burn, mint, send, receive, and read
+                                bit_mask: 0b0011_0111,
+                            }),
+                        ),
+                        (
+                            // Any permission
+                            FlexFuzzyNodeKey::Permission(FuzzySingleKey(None)),
+                            NodeValue::Permission(PermissionA {
+                                // Can redefine, delete, define, and read
+                                bit_mask: 0b0000_1111,
+                            }),
+                        ),
+                        (
+                            // Any (account, permission) pair
+                            FlexFuzzyNodeKey::AccountPermission(FlexFuzzyCompositeKey(None, None)),
+                            NodeValue::AccountPermission(AccountPermissionA {
+                                // Can revoke, grant, and read
+                                bit_mask: 0b0000_0111,
+                            }),
+                        ),
+                        (
+                            // Any executable
+                            FlexFuzzyNodeKey::Executable(FuzzySingleKey(None)),
+                            NodeValue::Executable(ExecutableA {
+                                // Can upgrade, unregister, register, and read
+                                bit_mask: 0b0000_1111,
+                            }),
+                        ),
+                    ])),
                 },
             ),
             (
@@ -159,6 +280,22 @@ mod tests {
                     )])),
                 },
             ),
+            (
+                SingleKey("steward".into()),
+                PermissionV {
+                    permission: FlexFuzzyTree(BTreeMap::from([(
+                        // Any account, inspector only
+                        FlexFuzzyNodeKey::AccountPermission(FlexFuzzyCompositeKey(
+                            None,
+                            Some("inspector".into()),
+                        )),
+                        NodeValue::AccountPermission(AccountPermissionA {
+                            // Can revoke and grant
+                            bit_mask: 0b0000_0110,
+                        }),
+                    )])),
+                },
+            ),
             (
                 SingleKey("everyman".into()),
                 PermissionV {
@@ -189,77 +326,338 @@ mod tests {
         .into()
     });
 
-    static ACCOUNT_PERMISSION: LazyLock<BTreeMap<AccountPermissionK, ()>> = LazyLock::new(|| {
-        [
-            (CompositeKey("alice".into(), "everyman".into()), ()),
-            (CompositeKey("bob".into(), "everyman".into()), ()),
-            (CompositeKey("carol".into(), "everyman".into()), ()),
-            (CompositeKey("dave".into(), "everyman".into()), ()),
-            (CompositeKey("eve".into(), "everyman".into()), ()),
-        ]
-        .into()
-    });
+    static ACCOUNT_PERMISSION: LazyLock<BTreeMap<AccountPermissionK, AccountPermissionV>> =
+        LazyLock::new(|| {
+            ["alice", "bob", "carol", "dave", "eve"]
+                .map(|name| {
+                    (
+                        CompositeKey(name.into(), "everyman".into()),
+                        AccountPermissionV,
+                    )
+                })
+                .into()
+        });
 
-    #[test]
-    fn almighty_reads_and_sends_others() {
-        let almighty = SingleKey("alice".into());
-        let mut world = state::World {
-            authorizer: AUTHORIZER.clone(),
+    /// The fixture world, where every account holds the everyman permission.
+    fn world() -> state::World {
+        state::World {
+            context: Context::default(),
             executable: EXECUTABLE.clone(),
             permission: PERMISSION.clone(),
+            account: ACCOUNT.clone(),
+            asset_definition: ASSET_DEFINITION.clone(),
             account_asset: ACCOUNT_ASSET.clone(),
             account_permission: ACCOUNT_PERMISSION.clone(),
-        };
+        }
+    }
+
+    /// Grants the account a permission on top of those it holds.
+    fn with_grant(mut world: state::World, account: &str, permission: &str) -> state::World {
+        world.account_permission.insert(
+            CompositeKey(account.into(), permission.into()),
+            AccountPermissionV,
+        );
         world
-            .account_permission
-            .insert(CompositeKey("alice".into(), "almighty".into()), ());
+    }
 
-        let supply_all = instruction::WasmInstruction {
-            executable: SingleKey("supply_all".to_string()),
-            args: serde_json::json!({
-                "asset": "rose",
-                "threshold": 100,
-                "supply_amount": 50,
-                // The almighty can supply from anyone
-                "supplier": "bob"
+    #[test]
+    fn almighty_reads_and_sends_others() {
+        on_every_backend!(with_grant(world(), "alice", "almighty"), |world| {
+            let almighty = SingleKey("alice".into());
+
+            let supply_all = instruction::WasmInstruction {
+                executable: SingleKey("supply_all".to_string()),
+                args: serde_json::json!({
+                    "asset": "rose",
+                    "threshold": 100,
+                    "supply_amount": 50,
+                    // The almighty can supply from anyone
+                    "supplier": "bob"
+                })
+                .to_string(),
+                read_policy: instruction::ReadPolicy::default(),
+                budget: instruction::Budget::default(),
+            };
+
+            println!("Initiating instruction");
+            supply_all
+                .initiate(almighty, &world)
+                .expect("should initiate")
+                .read_request()
+                .expect("should request read")
+                .read_approval(&world)
+                .expect("read request should be approved")
+                .read(&world)
+                .expect("should read")
+                .write_request()
+                .expect("should request write")
+                .write_approval()
+                .expect("write request should be approved")
+                .write(&mut world)
+                .expect("should write");
+
+            let expected = [
+                (
+                    CompositeKey("alice".into(), "rose".into()),
+                    AccountAssetV {
+                        balance: 500.into(),
+                    },
+                ),
+                (
+                    CompositeKey("bob".into(), "rose".into()),
+                    AccountAssetV { balance: 0.into() },
+                ),
+                (
+                    CompositeKey("carol".into(), "rose".into()),
+                    AccountAssetV {
+                        balance: 140.into(),
+                    },
+                ),
+                (
+                    CompositeKey("dave".into(), "rose".into()),
+                    AccountAssetV {
+                        balance: 140.into(),
+                    },
+                ),
+                (
+                    CompositeKey("eve".into(), "tulip".into()),
+                    AccountAssetV { balance: 90.into() },
+                ),
+            ];
+
+            assert_eq!(world.account_asset, expected.into());
+        });
+    }
+
+    #[test]
+    fn inspector_reads_but_does_not_send_others() {
+        on_every_backend!(with_grant(world(), "alice", "inspector"), |world| {
+            let inspector = SingleKey("alice".into());
+
+            let supply_all = instruction::WasmInstruction {
+                executable: SingleKey("supply_all".to_string()),
+                args: serde_json::json!({
+                    "asset": "rose",
+                    "threshold": 100,
+                    "supply_amount": 50,
+                    // The inspector cannot supply from others
+                    "supplier": "bob"
+                })
+                .to_string(),
+                read_policy: instruction::ReadPolicy::default(),
+                budget: instruction::Budget::default(),
+            };
+
+            println!("Initiating instruction");
+            let res = supply_all
+                .initiate(inspector, &world)
+                .expect("should initiate")
+                .read_request()
+                .expect("should request read")
+                .read_approval(&world)
+                .expect("read request should be approved")
+                .read(&world)
+                .expect("should read")
+                .write_request()
+                .expect("should request write")
+                .write_approval();
+
+            let Err(instruction::InstructionError::WriteRejected(rejected)) = res else {
+                panic!("write request should be rejected");
+            };
+            assert_eq!(
+                rejected,
+                vec![WriteRejection {
+                    key: NodeKey::AccountAsset(CompositeKey("bob".into(), "rose".into())),
+                    value: NodeValue::AccountAsset(AccountAssetE::Send),
+                    // Can receive and read, but not send
+                    required: 0b0000_0100,
+                    granted: 0b0000_0011,
+                }]
+            );
+
+            // No effect on the world state
+            let expected = [
+                (
+                    CompositeKey("alice".into(), "rose".into()),
+                    AccountAssetV {
+                        balance: 500.into(),
+                    },
+                ),
+                (
+                    CompositeKey("bob".into(), "rose".into()),
+                    AccountAssetV {
+                        balance: 100.into(),
+                    },
+                ),
+                (
+                    CompositeKey("carol".into(), "rose".into()),
+                    AccountAssetV { balance: 90.into() },
+                ),
+                (
+                    CompositeKey("dave".into(), "rose".into()),
+                    AccountAssetV { balance: 90.into() },
+                ),
+                (
+                    CompositeKey("eve".into(), "tulip".into()),
+                    AccountAssetV { balance: 90.into() },
+                ),
+            ];
+
+            assert_eq!(world.account_asset, expected.into());
+        });
+    }
+
+    #[test]
+    fn everyman_does_not_read_or_send_others() {
+        on_every_backend!(world(), |world| {
+            let everyman = SingleKey("alice".into());
+
+            let supply_all = instruction::WasmInstruction {
+                executable: SingleKey("supply_all".to_string()),
+                args: serde_json::json!({
+                    // The everyman cannot read others
+                    "asset": "rose",
+                    "threshold": 100,
+                    "supply_amount": 50,
+                    // The everyman cannot supply from others
+                    "supplier": "bob"
+                })
+                .to_string(),
+                read_policy: instruction::ReadPolicy::default(),
+                budget: instruction::Budget::default(),
+            };
+
+            println!("Initiating instruction");
+            let res = supply_all
+                .initiate(everyman, &world)
+                .expect("should initiate")
+                .read_request()
+                .expect("should request read")
+                .read_approval(&world);
+
+            let Err(instruction::InstructionError::ReadRejected(rejected)) = res else {
+                panic!("read request should be rejected");
+            };
+            assert_eq!(
+                rejected,
+                vec![ReadRejection {
+                    key: FuzzyNodeKey::AccountAsset(FuzzyCompositeKey(None, Some("rose".into()))),
+                    value: NodeValue::AccountAsset(AccountAssetR),
+                    // Can receive, but not read
+                    required: 0b0000_0001,
+                    granted: 0b0000_0010,
+                }]
+            );
+
+            // No effect on the world state
+            let expected = [
+                (
+                    CompositeKey("alice".into(), "rose".into()),
+                    AccountAssetV {
+                        balance: 500.into(),
+                    },
+                ),
+                (
+                    CompositeKey("bob".into(), "rose".into()),
+                    AccountAssetV {
+                        balance: 100.into(),
+                    },
+                ),
+                (
+                    CompositeKey("carol".into(), "rose".into()),
+                    AccountAssetV { balance: 90.into() },
+                ),
+                (
+                    CompositeKey("dave".into(), "rose".into()),
+                    AccountAssetV { balance: 90.into() },
+                ),
+                (
+                    CompositeKey("eve".into(), "tulip".into()),
+                    AccountAssetV { balance: 90.into() },
+                ),
+            ];
+
+            assert_eq!(world.account_asset, expected.into());
+        });
+    }
+
+    #[test]
+    fn everyman_reads_only_what_redaction_allows() {
+        let everyman = SingleKey("carol".into());
+        let mut world = world();
+        world.permission.insert(
+            SingleKey("agent".into()),
+            PermissionV {
+                permission: FlexFuzzyTree(BTreeMap::from([(
+                    // Bob, any asset
+                    FlexFuzzyNodeKey::AccountAsset(FlexFuzzyCompositeKey(
+                        Some(FlexKeyElem::That("bob".into())),
+                        None,
+                    )),
+                    NodeValue::AccountAsset(AccountAssetA {
+                        // Can send
+                        bit_mask: 0b0000_0100,
+                    }),
+                )])),
+            },
+        );
+        world.account_permission.insert(
+            CompositeKey("carol".into(), "agent".into()),
+            AccountPermissionV,
+        );
+
+        let supply_all = |read_policy| {
+            instruction::InstructionEnum::Wasm(instruction::WasmInstruction {
+                executable: SingleKey("supply_all".to_string()),
+                args: serde_json::json!({
+                    // The everyman reads only its own balance
+                    "asset": "rose",
+                    "threshold": 100,
+                    "supply_amount": 50,
+                    "supplier": "bob"
+                })
+                .to_string(),
+                read_policy,
+                budget: instruction::Budget::default(),
             })
-            .to_string(),
         };
 
-        println!("Initiating instruction");
-        supply_all
-            .initiate(almighty, &world)
-            .read_request()
-            .read_approval(&world)
-            .expect("read request should be approved")
-            .read(&world)
-            .expect("should read")
-            .write_request()
-            .write_approval()
-            .expect("write request should be approved")
-            .write(&mut world)
-            .expect("should write");
+        let res = supply_all(instruction::ReadPolicy::Strict).execute(everyman.clone(), &mut world);
+        assert!(
+            matches!(res, Err(instruction::InstructionError::ReadRejected(_))),
+            "strict read request should be rejected: {res:?}"
+        );
 
+        supply_all(instruction::ReadPolicy::Redact)
+            .execute(everyman, &mut world)
+            .expect("redacted read request should be approved");
+
+        // Only the everyman is supplied, as dave is out of its view
         let expected = [
             (
                 CompositeKey("alice".into(), "rose".into()),
-                AccountAssetV { balance: 500 },
+                AccountAssetV {
+                    balance: 500.into(),
+                },
             ),
             (
                 CompositeKey("bob".into(), "rose".into()),
-                AccountAssetV { balance: 0 },
+                AccountAssetV { balance: 50.into() },
             ),
             (
                 CompositeKey("carol".into(), "rose".into()),
-                AccountAssetV { balance: 140 },
+                AccountAssetV {
+                    balance: 140.into(),
+                },
             ),
             (
                 CompositeKey("dave".into(), "rose".into()),
-                AccountAssetV { balance: 140 },
+                AccountAssetV { balance: 90.into() },
             ),
             (
                 CompositeKey("eve".into(), "tulip".into()),
-                AccountAssetV { balance: 90 },
+                AccountAssetV { balance: 90.into() },
             ),
         ];
 
@@ -267,127 +665,1978 @@ mod tests {
     }
 
     #[test]
-    fn inspector_reads_but_does_not_send_others() {
-        let inspector = SingleKey("alice".into());
-        let mut world = state::World {
-            authorizer: AUTHORIZER.clone(),
-            executable: EXECUTABLE.clone(),
-            permission: PERMISSION.clone(),
-            account_asset: ACCOUNT_ASSET.clone(),
-            account_permission: ACCOUNT_PERMISSION.clone(),
-        };
+    fn overdrawn_write_leaves_world_unchanged() {
+        on_every_backend!(with_grant(world(), "alice", "almighty"), |world| {
+            let almighty = SingleKey("alice".into());
+
+            let supply_all = instruction::WasmInstruction {
+                executable: SingleKey("supply_all".to_string()),
+                args: serde_json::json!({
+                    "asset": "rose",
+                    "threshold": 100,
+                    // Carol and dave receive 60 each, but bob holds only 100
+                    "supply_amount": 60,
+                    "supplier": "bob"
+                })
+                .to_string(),
+                read_policy: instruction::ReadPolicy::default(),
+                budget: instruction::Budget::default(),
+            };
+
+            println!("Initiating instruction");
+            let res = supply_all
+                .initiate(almighty, &world)
+                .expect("should initiate")
+                .read_request()
+                .expect("should request read")
+                .read_approval(&world)
+                .expect("read request should be approved")
+                .read(&world)
+                .expect("should read")
+                .write_request()
+                .expect("should request write")
+                .write_approval()
+                .expect("write request should be approved")
+                .write(&mut world);
+
+            assert!(
+                matches!(
+                    res,
+                    Err(instruction::InstructionError::StateViolation(
+                        state::WriteError::InsufficientBalance {
+                            balance,
+                            amount,
+                            ..
+                        }
+                    )) if balance == 100.into() && amount == 120.into()
+                ),
+                "write request should violate the state"
+            );
+
+            // No effect on the world state
+            assert_eq!(world.account_asset, *ACCOUNT_ASSET);
+        });
+    }
+
+    #[test]
+    fn almighty_burns_and_mints_others() {
+        on_every_backend!(with_grant(world(), "alice", "almighty"), |world| {
+            let almighty = SingleKey("alice".into());
+
+            let swap = instruction::WasmInstruction {
+                executable: SingleKey("swap".to_string()),
+                args: serde_json::json!({
+                    // The almighty can burn and mint for anyone
+                    "account": "bob",
+                    "from": "rose",
+                    "to": "tulip",
+                    "amount": 30
+                })
+                .to_string(),
+                read_policy: instruction::ReadPolicy::default(),
+                budget: instruction::Budget::default(),
+            };
+
+            println!("Initiating instruction");
+            swap.initiate(almighty, &world)
+                .expect("should initiate")
+                .read_request()
+                .expect("should request read")
+                .read_approval(&world)
+                .expect("read request should be approved")
+                .read(&world)
+                .expect("should read")
+                .write_request()
+                .expect("should request write")
+                .write_approval()
+                .expect("write request should be approved")
+                .write(&mut world)
+                .expect("should write");
+
+            let expected = [
+                (
+                    CompositeKey("alice".into(), "rose".into()),
+                    AccountAssetV {
+                        balance: 500.into(),
+                    },
+                ),
+                (
+                    CompositeKey("bob".into(), "rose".into()),
+                    AccountAssetV { balance: 70.into() },
+                ),
+                (
+                    CompositeKey("bob".into(), "tulip".into()),
+                    AccountAssetV { balance: 30.into() },
+                ),
+                (
+                    CompositeKey("carol".into(), "rose".into()),
+                    AccountAssetV { balance: 90.into() },
+                ),
+                (
+                    CompositeKey("dave".into(), "rose".into()),
+                    AccountAssetV { balance: 90.into() },
+                ),
+                (
+                    CompositeKey("eve".into(), "tulip".into()),
+                    AccountAssetV { balance: 90.into() },
+                ),
+            ];
+
+            assert_eq!(world.account_asset, expected.into());
+            assert_eq!(
+                world.asset_definition[&SingleKey("rose".into())].total_supply,
+                750.into()
+            );
+            assert_eq!(
+                world.asset_definition[&SingleKey("tulip".into())].total_supply,
+                120.into()
+            );
+            world.audit_supply().expect("supply should add up");
+        });
+    }
+
+    #[test]
+    fn almighty_defines_and_mints_new_asset() {
+        on_every_backend!(with_grant(world(), "alice", "almighty"), |world| {
+            let almighty = SingleKey("alice".into());
+
+            let tx = transaction::Transaction {
+                authority: almighty,
+                instructions: vec![
+                    instruction::InstructionEnum::from(
+                        instruction::BuiltinInstruction::RegisterAssetDefinition {
+                            asset: SingleKey("lily".into()),
+                            scale: 0,
+                        },
+                    ),
+                    instruction::InstructionEnum::from(instruction::BuiltinInstruction::Mint {
+                        account: SingleKey("bob".into()),
+                        asset: "lily".into(),
+                        amount: 40.into(),
+                    }),
+                ],
+            };
+            tx.execute(&mut world).expect("should define and mint");
+
+            assert_eq!(
+                world.asset_definition[&SingleKey("lily".into())],
+                AssetDefinitionV {
+                    owner: SingleKey("alice".into()),
+                    scale: 0,
+                    total_supply: 40.into(),
+                }
+            );
+            assert_eq!(
+                world.account_asset[&CompositeKey("bob".into(), "lily".into())],
+                AccountAssetV { balance: 40.into() }
+            );
+            world.audit_supply().expect("supply should add up");
+        });
+    }
+
+    #[test]
+    fn amounts_follow_asset_scale() {
+        on_every_backend!(with_grant(world(), "alice", "almighty"), |world| {
+            let almighty = SingleKey("alice".into());
+
+            let tx = transaction::Transaction {
+                authority: almighty.clone(),
+                instructions: vec![
+                    instruction::InstructionEnum::from(
+                        instruction::BuiltinInstruction::RegisterAssetDefinition {
+                            asset: SingleKey("lily".into()),
+                            scale: 2,
+                        },
+                    ),
+                    // 1.5 is kept as 1.50
+                    instruction::InstructionEnum::from(instruction::BuiltinInstruction::Mint {
+                        account: SingleKey("bob".into()),
+                        asset: "lily".into(),
+                        amount: Numeric::new(15, 1),
+                    }),
+                ],
+            };
+            tx.execute(&mut world).expect("should define and mint");
+
+            assert_eq!(
+                world.account_asset[&CompositeKey("bob".into(), "lily".into())],
+                AccountAssetV {
+                    balance: Numeric::new(150, 2)
+                }
+            );
+
+            // 0.001 is finer than the asset allows
+            let mint = instruction::InstructionEnum::from(instruction::BuiltinInstruction::Mint {
+                account: SingleKey("bob".into()),
+                asset: "lily".into(),
+                amount: Numeric::new(1, 3),
+            });
+            let res = mint.execute(almighty, &mut world);
+
+            assert!(
+                matches!(
+                    res,
+                    Err(instruction::InstructionError::StateViolation(
+                        state::WriteError::UnrepresentableAmount { scale: 2, .. }
+                    ))
+                ),
+                "minting below the asset scale should fail: {res:?}"
+            );
+            assert_eq!(
+                world.asset_definition[&SingleKey("lily".into())].total_supply,
+                Numeric::new(150, 2)
+            );
+            world.audit_supply().expect("supply should add up");
+        });
+    }
+
+    #[test]
+    fn undefined_asset_is_rejected() {
+        on_every_backend!(with_grant(world(), "alice", "almighty"), |world| {
+            let almighty = SingleKey("alice".into());
+
+            let mint = instruction::InstructionEnum::from(instruction::BuiltinInstruction::Mint {
+                account: SingleKey("bob".into()),
+                asset: "lily".into(),
+                amount: 40.into(),
+            });
+            let res = mint.execute(almighty, &mut world);
+
+            assert!(
+                matches!(
+                    res,
+                    Err(instruction::InstructionError::StateViolation(
+                        state::WriteError::NoAssetDefinition(SingleKey(ref asset))
+                    )) if asset == "lily"
+                ),
+                "minting an undefined asset should fail: {res:?}"
+            );
+            assert_eq!(world.account_asset, *ACCOUNT_ASSET);
+        });
+    }
+
+    #[test]
+    fn self_transfer_is_rejected() {
+        on_every_backend!(with_grant(world(), "alice", "almighty"), |world| {
+            let almighty = SingleKey("alice".into());
+
+            let transfer =
+                instruction::InstructionEnum::from(instruction::BuiltinInstruction::Transfer {
+                    from: SingleKey("bob".into()),
+                    to: SingleKey("bob".into()),
+                    asset: "rose".into(),
+                    amount: 40.into(),
+                });
+            let res = transfer.execute(almighty, &mut world);
+
+            assert!(
+                matches!(
+                    res,
+                    Err(instruction::InstructionError::SelfTransfer(SingleKey(ref account)))
+                        if account == "bob"
+                ),
+                "transferring to the sender should fail: {res:?}"
+            );
+            assert_eq!(world.account_asset, *ACCOUNT_ASSET);
+        });
+    }
+
+    #[test]
+    fn audit_reports_supply_mismatch() {
+        let mut world = world();
+        world.audit_supply().expect("supply should add up");
+
+        // Balances tampered with outside of any write
+        world.account_asset.insert(
+            CompositeKey("bob".into(), "rose".into()),
+            AccountAssetV {
+                balance: 101.into(),
+            },
+        );
+
+        assert_eq!(
+            world.audit_supply(),
+            Err(state::SupplyMismatch {
+                asset: SingleKey("rose".into()),
+                total_supply: 780.into(),
+                held: Some(781.into()),
+            })
+        );
+    }
+
+    #[test]
+    fn burn_beyond_total_supply_is_rejected() {
+        let almighty = SingleKey("alice".into());
+        let mut world = with_grant(world(), "alice", "almighty");
+        // Supply tampered with outside of any write, below what bob alone holds
         world
-            .account_permission
-            .insert(CompositeKey("alice".into(), "inspector".into()), ());
+            .asset_definition
+            .get_mut(&SingleKey("rose".into()))
+            .expect("rose should be defined")
+            .total_supply = 50.into();
 
-        let supply_all = instruction::WasmInstruction {
-            executable: SingleKey("supply_all".to_string()),
+        let burn = instruction::InstructionEnum::from(instruction::BuiltinInstruction::Burn {
+            account: SingleKey("bob".into()),
+            asset: "rose".into(),
+            amount: 100.into(),
+        });
+        let res = burn.execute(almighty, &mut world);
+
+        assert!(
+            matches!(
+                res,
+                Err(instruction::InstructionError::StateViolation(
+                    state::WriteError::SupplyUnderflow { .. }
+                ))
+            ),
+            "burning beyond the total supply should fail: {res:?}"
+        );
+        assert_eq!(world.account_asset, *ACCOUNT_ASSET);
+    }
+
+    #[test]
+    fn instruction_sees_block_context() {
+        let almighty = SingleKey("alice".into());
+        let mut world = with_grant(world(), "alice", "almighty");
+        world.context = Context {
+            height: 10,
+            timestamp: 1_000,
+        };
+
+        let swap = instruction::InstructionEnum::Wasm(instruction::WasmInstruction {
+            executable: SingleKey("swap".to_string()),
             args: serde_json::json!({
-                "asset": "rose",
-                "threshold": 100,
-                "supply_amount": 50,
-                // The inspector cannot supply from others
-                "supplier": "bob"
+                "account": "bob",
+                "from": "rose",
+                "to": "tulip",
+                "amount": 30,
+                // Already passed at the current block
+                "deadline": 999
+            })
+            .to_string(),
+            read_policy: instruction::ReadPolicy::default(),
+            budget: instruction::Budget::default(),
+        });
+
+        swap.execute(almighty, &mut world).expect("should execute");
+
+        assert_eq!(world.account_asset, *ACCOUNT_ASSET);
+    }
+
+    #[test]
+    fn instruction_refers_to_this_account() {
+        on_every_backend!(with_grant(world(), "alice", "almighty"), |world| {
+            let almighty = SingleKey("alice".into());
+
+            let swap = instruction::InstructionEnum::Wasm(instruction::WasmInstruction {
+                executable: SingleKey("swap".to_string()),
+                args: serde_json::json!({
+                    // No account given, so the swap applies to the authority
+                    "from": "rose",
+                    "to": "tulip",
+                    "amount": 30
+                })
+                .to_string(),
+                read_policy: instruction::ReadPolicy::default(),
+                budget: instruction::Budget::default(),
+            });
+
+            swap.execute(almighty, &mut world).expect("should execute");
+
+            let expected = [
+                (
+                    CompositeKey("alice".into(), "rose".into()),
+                    AccountAssetV {
+                        balance: 470.into(),
+                    },
+                ),
+                (
+                    CompositeKey("alice".into(), "tulip".into()),
+                    AccountAssetV { balance: 30.into() },
+                ),
+                (
+                    CompositeKey("bob".into(), "rose".into()),
+                    AccountAssetV {
+                        balance: 100.into(),
+                    },
+                ),
+                (
+                    CompositeKey("carol".into(), "rose".into()),
+                    AccountAssetV { balance: 90.into() },
+                ),
+                (
+                    CompositeKey("dave".into(), "rose".into()),
+                    AccountAssetV { balance: 90.into() },
+                ),
+                (
+                    CompositeKey("eve".into(), "tulip".into()),
+                    AccountAssetV { balance: 90.into() },
+                ),
+            ];
+
+            assert_eq!(world.account_asset, expected.into());
+        });
+    }
+
+    #[test]
+    fn everyman_does_not_burn_or_mint() {
+        let everyman = SingleKey("alice".into());
+        let world = world();
+
+        let swap = instruction::WasmInstruction {
+            executable: SingleKey("swap".to_string()),
+            args: serde_json::json!({
+                // The everyman can read its own account but cannot burn or mint
+                "account": "alice",
+                "from": "rose",
+                "to": "tulip",
+                "amount": 30
             })
             .to_string(),
+            read_policy: instruction::ReadPolicy::default(),
+            budget: instruction::Budget::default(),
         };
 
         println!("Initiating instruction");
-        let res = supply_all
-            .initiate(inspector, &world)
+        let res = swap
+            .initiate(everyman, &world)
+            .expect("should initiate")
             .read_request()
+            .expect("should request read")
             .read_approval(&world)
             .expect("read request should be approved")
             .read(&world)
             .expect("should read")
             .write_request()
+            .expect("should request write")
             .write_approval();
 
-        assert!(res.is_err(), "write request should be rejected");
+        assert!(
+            matches!(res, Err(instruction::InstructionError::WriteRejected(_))),
+            "write request should be rejected"
+        );
+    }
 
-        // No effect on the world state
-        let expected = [
-            (
-                CompositeKey("alice".into(), "rose".into()),
-                AccountAssetV { balance: 500 },
-            ),
-            (
-                CompositeKey("bob".into(), "rose".into()),
-                AccountAssetV { balance: 100 },
-            ),
-            (
-                CompositeKey("carol".into(), "rose".into()),
-                AccountAssetV { balance: 90 },
-            ),
-            (
-                CompositeKey("dave".into(), "rose".into()),
-                AccountAssetV { balance: 90 },
-            ),
-            (
-                CompositeKey("eve".into(), "tulip".into()),
-                AccountAssetV { balance: 90 },
-            ),
-        ];
+    #[test]
+    fn everyman_transfers_own_with_builtin() {
+        on_every_backend!(world(), |world| {
+            let everyman = SingleKey("alice".into());
 
-        assert_eq!(world.account_asset, expected.into());
+            let transfer =
+                instruction::InstructionEnum::from(instruction::BuiltinInstruction::Transfer {
+                    from: SingleKey("alice".into()),
+                    to: SingleKey("bob".into()),
+                    asset: "rose".into(),
+                    amount: 200.into(),
+                });
+
+            transfer
+                .execute(everyman, &mut world)
+                .expect("should transfer");
+
+            let expected = [
+                (
+                    CompositeKey("alice".into(), "rose".into()),
+                    AccountAssetV {
+                        balance: 300.into(),
+                    },
+                ),
+                (
+                    CompositeKey("bob".into(), "rose".into()),
+                    AccountAssetV {
+                        balance: 300.into(),
+                    },
+                ),
+                (
+                    CompositeKey("carol".into(), "rose".into()),
+                    AccountAssetV { balance: 90.into() },
+                ),
+                (
+                    CompositeKey("dave".into(), "rose".into()),
+                    AccountAssetV { balance: 90.into() },
+                ),
+                (
+                    CompositeKey("eve".into(), "tulip".into()),
+                    AccountAssetV { balance: 90.into() },
+                ),
+            ];
+
+            assert_eq!(world.account_asset, expected.into());
+        });
     }
 
     #[test]
-    fn everyman_does_not_read_or_send_others() {
-        let everyman = SingleKey("alice".into());
-        let world = state::World {
-            authorizer: AUTHORIZER.clone(),
-            executable: EXECUTABLE.clone(),
-            permission: PERMISSION.clone(),
-            account_asset: ACCOUNT_ASSET.clone(),
-            account_permission: ACCOUNT_PERMISSION.clone(),
+    fn everyman_does_not_mint_with_builtin() {
+        on_every_backend!(world(), |world| {
+            let everyman = SingleKey("alice".into());
+
+            let mint = instruction::InstructionEnum::from(instruction::BuiltinInstruction::Mint {
+                account: SingleKey("alice".into()),
+                asset: "rose".into(),
+                amount: 200.into(),
+            });
+
+            let res = mint.execute(everyman, &mut world);
+
+            assert!(
+                matches!(res, Err(instruction::InstructionError::WriteRejected(_))),
+                "write request should be rejected"
+            );
+            assert_eq!(world.account_asset, *ACCOUNT_ASSET);
+        });
+    }
+
+    #[test]
+    fn almighty_registers_and_unregisters_accounts() {
+        on_every_backend!(with_grant(world(), "alice", "almighty"), |world| {
+            let almighty = SingleKey("alice".into());
+
+            let register = instruction::InstructionEnum::from(
+                instruction::BuiltinInstruction::RegisterAccount {
+                    account: SingleKey("frank".into()),
+                },
+            );
+            register
+                .execute(almighty.clone(), &mut world)
+                .expect("should register");
+            assert!(world.account.contains_key(&SingleKey("frank".into())));
+
+            let unregister = instruction::InstructionEnum::from(
+                instruction::BuiltinInstruction::UnregisterAccount {
+                    account: SingleKey("frank".into()),
+                },
+            );
+            unregister
+                .execute(almighty, &mut world)
+                .expect("should unregister");
+            assert_eq!(world.account, *ACCOUNT);
+        });
+    }
+
+    #[test]
+    fn everyman_does_not_register_accounts() {
+        on_every_backend!(world(), |world| {
+            let everyman = SingleKey("alice".into());
+
+            let register = instruction::InstructionEnum::from(
+                instruction::BuiltinInstruction::RegisterAccount {
+                    account: SingleKey("frank".into()),
+                },
+            );
+            let res = register.execute(everyman, &mut world);
+
+            assert!(
+                matches!(res, Err(instruction::InstructionError::WriteRejected(_))),
+                "write request should be rejected"
+            );
+            assert_eq!(world.account, *ACCOUNT);
+        });
+    }
+
+    #[test]
+    fn unregistered_account_does_not_receive() {
+        on_every_backend!(with_grant(world(), "alice", "almighty"), |world| {
+            let almighty = SingleKey("alice".into());
+
+            let transfer =
+                instruction::InstructionEnum::from(instruction::BuiltinInstruction::Transfer {
+                    from: SingleKey("alice".into()),
+                    to: SingleKey("frank".into()),
+                    asset: "rose".into(),
+                    amount: 200.into(),
+                });
+            let res = transfer.execute(almighty, &mut world);
+
+            assert!(
+                matches!(
+                    res,
+                    Err(instruction::InstructionError::StateViolation(
+                        state::WriteError::NoAccount(SingleKey(ref account))
+                    )) if account == "frank"
+                ),
+                "transfer to an unregistered account should fail: {res:?}"
+            );
+            assert_eq!(world.account_asset, *ACCOUNT_ASSET);
+        });
+    }
+
+    #[test]
+    fn account_holding_assets_is_not_unregistered() {
+        on_every_backend!(with_grant(world(), "alice", "almighty"), |world| {
+            let almighty = SingleKey("alice".into());
+
+            let unregister = instruction::InstructionEnum::from(
+                instruction::BuiltinInstruction::UnregisterAccount {
+                    account: SingleKey("bob".into()),
+                },
+            );
+            let res = unregister.execute(almighty.clone(), &mut world);
+
+            assert!(
+                matches!(
+                    res,
+                    Err(instruction::InstructionError::StateViolation(
+                        state::WriteError::AccountNotEmpty(_)
+                    ))
+                ),
+                "unregistering an account holding assets should fail: {res:?}"
+            );
+            assert_eq!(world.account, *ACCOUNT);
+
+            // Emptied, the account goes away along with its balance entry
+            let tx = transaction::Transaction {
+                authority: almighty,
+                instructions: vec![
+                    instruction::InstructionEnum::from(instruction::BuiltinInstruction::Burn {
+                        account: SingleKey("bob".into()),
+                        asset: "rose".into(),
+                        amount: 100.into(),
+                    }),
+                    instruction::InstructionEnum::from(
+                        instruction::BuiltinInstruction::UnregisterAccount {
+                            account: SingleKey("bob".into()),
+                        },
+                    ),
+                ],
+            };
+            tx.execute(&mut world).expect("should unregister");
+
+            assert!(!world.account.contains_key(&SingleKey("bob".into())));
+            assert!(
+                !world
+                    .account_asset
+                    .contains_key(&CompositeKey("bob".into(), "rose".into()))
+            );
+        });
+    }
+
+    #[test]
+    fn almighty_defines_grants_and_revokes_permission() {
+        on_every_backend!(with_grant(world(), "alice", "almighty"), |world| {
+            let almighty = SingleKey("alice".into());
+
+            let minter = FlexFuzzyTree(BTreeMap::from([(
+                // Any account, rose only
+                FlexFuzzyNodeKey::AccountAsset(FlexFuzzyCompositeKey(None, Some("rose".into()))),
+                NodeValue::AccountAsset(AccountAssetA {
+                    // Can mint
+                    bit_mask: 0b0001_0000,
+                }),
+            )]));
+            let tx = transaction::Transaction {
+                authority: almighty.clone(),
+                instructions: vec![
+                    instruction::InstructionEnum::from(
+                        instruction::BuiltinInstruction::DefinePermission {
+                            permission: SingleKey("minter".into()),
+                            allow: minter.clone(),
+                        },
+                    ),
+                    instruction::InstructionEnum::from(
+                        instruction::BuiltinInstruction::GrantPermission {
+                            account: SingleKey("bob".into()),
+                            permission: "minter".into(),
+                        },
+                    ),
+                ],
+            };
+            tx.execute(&mut world).expect("should define and grant");
+            assert_eq!(
+                world.permission[&SingleKey("minter".into())],
+                PermissionV { permission: minter }
+            );
+
+            let mint = || {
+                instruction::InstructionEnum::from(instruction::BuiltinInstruction::Mint {
+                    account: SingleKey("bob".into()),
+                    asset: "rose".into(),
+                    amount: 10.into(),
+                })
+            };
+            mint()
+                .execute(SingleKey("bob".into()), &mut world)
+                .expect("minter should mint");
+            assert_eq!(
+                world.account_asset[&CompositeKey("bob".into(), "rose".into())],
+                AccountAssetV {
+                    balance: 110.into()
+                }
+            );
+
+            let tx = transaction::Transaction {
+                authority: almighty,
+                instructions: vec![
+                    instruction::InstructionEnum::from(
+                        instruction::BuiltinInstruction::RevokePermission {
+                            account: SingleKey("bob".into()),
+                            permission: "minter".into(),
+                        },
+                    ),
+                    instruction::InstructionEnum::from(
+                        instruction::BuiltinInstruction::DeletePermission {
+                            permission: SingleKey("minter".into()),
+                        },
+                    ),
+                ],
+            };
+            tx.execute(&mut world).expect("should revoke and delete");
+            assert_eq!(world.permission, *PERMISSION);
+
+            let res = mint().execute(SingleKey("bob".into()), &mut world);
+            assert!(
+                matches!(res, Err(instruction::InstructionError::WriteRejected(_))),
+                "revoked permission should not mint: {res:?}"
+            );
+        });
+    }
+
+    #[test]
+    fn steward_grants_inspector_only() {
+        on_every_backend!(with_grant(world(), "carol", "steward"), |world| {
+            let steward = SingleKey("carol".into());
+
+            let grant = |permission: &str| {
+                instruction::InstructionEnum::from(
+                    instruction::BuiltinInstruction::GrantPermission {
+                        account: SingleKey("dave".into()),
+                        permission: permission.into(),
+                    },
+                )
+            };
+            grant("inspector")
+                .execute(steward.clone(), &mut world)
+                .expect("steward should grant inspector");
+            assert!(
+                world
+                    .account_permission
+                    .contains_key(&CompositeKey("dave".into(), "inspector".into()))
+            );
+
+            let res = grant("almighty").execute(steward, &mut world);
+            assert!(
+                matches!(res, Err(instruction::InstructionError::WriteRejected(_))),
+                "steward should not grant almighty: {res:?}"
+            );
+            assert!(
+                !world
+                    .account_permission
+                    .contains_key(&CompositeKey("dave".into(), "almighty".into()))
+            );
+        });
+    }
+
+    #[test]
+    fn granted_permission_is_not_deleted() {
+        on_every_backend!(with_grant(world(), "alice", "almighty"), |world| {
+            let almighty = SingleKey("alice".into());
+
+            let delete = instruction::InstructionEnum::from(
+                instruction::BuiltinInstruction::DeletePermission {
+                    permission: SingleKey("everyman".into()),
+                },
+            );
+            let res = delete.execute(almighty, &mut world);
+
+            assert!(
+                matches!(
+                    res,
+                    Err(instruction::InstructionError::StateViolation(
+                        state::WriteError::PermissionInUse(SingleKey(ref permission))
+                    )) if permission == "everyman"
+                ),
+                "deleting a granted permission should fail: {res:?}"
+            );
+            assert_eq!(world.permission, *PERMISSION);
+        });
+    }
+
+    #[test]
+    fn transaction_sees_earlier_writes() {
+        on_every_backend!(with_grant(world(), "alice", "almighty"), |world| {
+            let transaction = transaction::Transaction {
+                authority: SingleKey("alice".into()),
+                instructions: vec![
+                    instruction::InstructionEnum::Wasm(instruction::WasmInstruction {
+                        executable: SingleKey("swap".to_string()),
+                        args: serde_json::json!({
+                            "account": "bob",
+                            "from": "rose",
+                            "to": "tulip",
+                            "amount": 30
+                        })
+                        .to_string(),
+                        read_policy: instruction::ReadPolicy::default(),
+                        budget: instruction::Budget::default(),
+                    }),
+                    instruction::InstructionEnum::Wasm(instruction::WasmInstruction {
+                        executable: SingleKey("supply_all".to_string()),
+                        args: serde_json::json!({
+                            "asset": "rose",
+                            // Bob falls below the threshold only after the swap
+                            "threshold": 100,
+                            "supply_amount": 50,
+                            "supplier": "alice"
+                        })
+                        .to_string(),
+                        read_policy: instruction::ReadPolicy::default(),
+                        budget: instruction::Budget::default(),
+                    }),
+                ],
+            };
+
+            transaction
+                .execute(&mut world)
+                .expect("transaction should succeed");
+
+            let expected = [
+                (
+                    CompositeKey("alice".into(), "rose".into()),
+                    AccountAssetV {
+                        balance: 350.into(),
+                    },
+                ),
+                (
+                    CompositeKey("bob".into(), "rose".into()),
+                    AccountAssetV {
+                        balance: 120.into(),
+                    },
+                ),
+                (
+                    CompositeKey("bob".into(), "tulip".into()),
+                    AccountAssetV { balance: 30.into() },
+                ),
+                (
+                    CompositeKey("carol".into(), "rose".into()),
+                    AccountAssetV {
+                        balance: 140.into(),
+                    },
+                ),
+                (
+                    CompositeKey("dave".into(), "rose".into()),
+                    AccountAssetV {
+                        balance: 140.into(),
+                    },
+                ),
+                (
+                    CompositeKey("eve".into(), "tulip".into()),
+                    AccountAssetV { balance: 90.into() },
+                ),
+            ];
+
+            assert_eq!(world.account_asset, expected.into());
+        });
+    }
+
+    #[test]
+    fn failed_transaction_leaves_world_unchanged() {
+        on_every_backend!(with_grant(world(), "alice", "almighty"), |world| {
+            let transaction = transaction::Transaction {
+                authority: SingleKey("alice".into()),
+                instructions: vec![
+                    instruction::InstructionEnum::Wasm(instruction::WasmInstruction {
+                        executable: SingleKey("swap".to_string()),
+                        args: serde_json::json!({
+                            "account": "bob",
+                            "from": "rose",
+                            "to": "tulip",
+                            "amount": 30
+                        })
+                        .to_string(),
+                        read_policy: instruction::ReadPolicy::default(),
+                        budget: instruction::Budget::default(),
+                    }),
+                    instruction::InstructionEnum::Wasm(instruction::WasmInstruction {
+                        executable: SingleKey("supply_all".to_string()),
+                        args: serde_json::json!({
+                            "asset": "rose",
+                            "threshold": 100,
+                            "supply_amount": 50,
+                            // Eve holds no rose to supply from
+                            "supplier": "eve"
+                        })
+                        .to_string(),
+                        read_policy: instruction::ReadPolicy::default(),
+                        budget: instruction::Budget::default(),
+                    }),
+                ],
+            };
+
+            let res = transaction.execute(&mut world);
+
+            assert!(
+                matches!(
+                    res,
+                    Err(transaction::TransactionError { index: 1, ref source })
+                        if matches!(
+                            **source,
+                            instruction::InstructionError::StateViolation(
+                                state::WriteError::NoBalance(_)
+                            )
+                        )
+                ),
+                "second instruction should violate the state"
+            );
+
+            // The first instruction is discarded as well
+            assert_eq!(world.account_asset, *ACCOUNT_ASSET);
+        });
+    }
+
+    #[test]
+    fn unknown_executable_is_reported() {
+        let world = world();
+
+        let unknown = instruction::WasmInstruction {
+            executable: SingleKey("unknown".to_string()),
+            args: String::new(),
+            read_policy: instruction::ReadPolicy::default(),
+            budget: instruction::Budget::default(),
         };
 
+        let res = unknown.initiate(SingleKey("alice".into()), &world);
+
+        assert!(
+            matches!(res, Err(instruction::InstructionError::ExecutableNotFound(SingleKey(ref key))) if key == "unknown"),
+            "executable should not be found"
+        );
+    }
+
+    #[test]
+    fn guest_trap_is_reported() {
+        let world = world();
+
         let supply_all = instruction::WasmInstruction {
+            executable: SingleKey("supply_all".to_string()),
+            // Missing fields make the guest panic while parsing args
+            args: serde_json::json!({}).to_string(),
+            read_policy: instruction::ReadPolicy::default(),
+            budget: instruction::Budget::default(),
+        };
+
+        let res = supply_all
+            .initiate(SingleKey("alice".into()), &world)
+            .expect("should initiate")
+            .read_request();
+
+        assert!(
+            matches!(res, Err(instruction::InstructionError::GuestTrap { .. })),
+            "guest should trap"
+        );
+    }
+
+    #[test]
+    fn exhausted_fuel_is_reported() {
+        let mut world = world();
+
+        let supply_all = instruction::InstructionEnum::Wasm(instruction::WasmInstruction {
             executable: SingleKey("supply_all".to_string()),
             args: serde_json::json!({
-                // The everyman cannot read others
                 "asset": "rose",
                 "threshold": 100,
                 "supply_amount": 50,
-                // The everyman cannot supply from others
-                "supplier": "bob"
+                "supplier": "alice"
             })
             .to_string(),
+            read_policy: instruction::ReadPolicy::default(),
+            budget: instruction::Budget {
+                instruction: instruction::Limits {
+                    fuel: 1_000,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        });
+
+        let res = supply_all.execute(SingleKey("alice".into()), &mut world);
+
+        assert!(
+            matches!(
+                res,
+                Err(instruction::InstructionError::BudgetExhausted(
+                    instruction::Exhausted::Fuel
+                ))
+            ),
+            "instruction should run out of fuel"
+        );
+        assert_eq!(world.account_asset, *ACCOUNT_ASSET);
+    }
+
+    #[test]
+    fn exhausted_memory_is_reported() {
+        let world = world();
+
+        let supply_all = instruction::WasmInstruction {
+            executable: SingleKey("supply_all".to_string()),
+            args: String::new(),
+            read_policy: instruction::ReadPolicy::default(),
+            budget: instruction::Budget {
+                authorizer: instruction::Limits {
+                    // Less than a single Wasm page
+                    memory: 1 << 10,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
         };
 
-        println!("Initiating instruction");
-        let res = supply_all
-            .initiate(everyman, &world)
-            .read_request()
-            .read_approval(&world);
+        let res = supply_all.initiate(SingleKey("alice".into()), &world);
 
-        assert!(res.is_err(), "read request should be rejected");
+        assert!(
+            matches!(
+                res,
+                Err(instruction::InstructionError::BudgetExhausted(
+                    instruction::Exhausted::Memory
+                ))
+            ),
+            "authorizer should run out of memory"
+        );
+    }
 
-        // No effect on the world state
-        let expected = [
-            (
-                CompositeKey("alice".into(), "rose".into()),
-                AccountAssetV { balance: 500 },
+    #[test]
+    fn builtin_authorizer_keeps_its_limits() {
+        let mut world = with_grant(world(), "alice", "almighty");
+
+        let mint = instruction::InstructionEnum::Builtin(instruction::Builtin {
+            instruction: instruction::BuiltinInstruction::Mint {
+                account: SingleKey("bob".into()),
+                asset: "rose".into(),
+                amount: 40.into(),
+            },
+            authorizer: instruction::Limits {
+                // Less than a single Wasm page
+                memory: 1 << 10,
+                ..Default::default()
+            },
+        });
+        let json = serde_json::to_string(&mint).expect("should serialize");
+        assert_eq!(
+            serde_json::from_str::<instruction::InstructionEnum>(&json)
+                .expect("should deserialize"),
+            mint
+        );
+
+        let res = mint.execute(SingleKey("alice".into()), &mut world);
+
+        assert!(
+            matches!(
+                res,
+                Err(instruction::InstructionError::BudgetExhausted(
+                    instruction::Exhausted::Memory
+                ))
             ),
-            (
-                CompositeKey("bob".into(), "rose".into()),
-                AccountAssetV { balance: 100 },
+            "authorizer should run out of memory: {res:?}"
+        );
+        assert_eq!(world.account_asset, *ACCOUNT_ASSET);
+    }
+
+    #[test]
+    fn engine_without_fuel_is_rejected() {
+        let engine = wasmtime::Engine::default();
+
+        let res = ExecutableV::new(&engine, &EXECUTABLE[&SingleKey("swap".into())].bytes);
+        assert!(res.is_err(), "compiling on an unmetered engine should fail");
+
+        let res = genesis::Genesis::load("fixtures/genesis.json", &engine);
+        assert!(
+            matches!(res, Err(genesis::GenesisError::Compile { .. })),
+            "genesis on an unmetered engine should fail"
+        );
+    }
+
+    #[test]
+    fn components_must_match_their_world() {
+        let almighty = SingleKey("alice".into());
+        let mut world = with_grant(world(), "alice", "almighty");
+        let read = |guest: &str| {
+            std::fs::read(format!("../target/wasm32-wasip2/debug/{guest}.wasm"))
+                .expect("component should have been built")
+        };
+        let register = |executable: &str, bytes| {
+            instruction::InstructionEnum::from(
+                instruction::BuiltinInstruction::RegisterExecutable {
+                    executable: SingleKey(executable.into()),
+                    bytes,
+                },
+            )
+        };
+
+        let res =
+            register("authorizer_again", read("authorizer")).execute(almighty.clone(), &mut world);
+        assert!(
+            matches!(
+                res,
+                Err(instruction::InstructionError::StateViolation(
+                    state::WriteError::InvalidExecutable { .. }
+                ))
             ),
+            "authorizer should not be registered as an executable: {res:?}"
+        );
+
+        let swap = EXECUTABLE[&SingleKey("swap".into())].clone();
+        let res = world.set_authorizer(swap);
+        assert!(
+            matches!(res, Err(instruction::InstructionError::Instantiation(_))),
+            "instruction should not be set as the authorizer"
+        );
+
+        register("swap_again", read("swap"))
+            .execute(almighty, &mut world)
+            .expect("instruction should be registered as an executable");
+        assert_eq!(
+            world.executable[&SingleKey("swap_again".into())],
+            world.executable[&SingleKey("swap".into())],
+            "same bytes should hash the same"
+        );
+        world
+            .set_authorizer(EXECUTABLE[&SingleKey("authorizer".into())].clone())
+            .expect("authorizer should be set as the authorizer");
+    }
+
+    #[test]
+    fn almighty_upgrades_and_unregisters_executable() {
+        on_every_backend!(with_grant(world(), "alice", "almighty"), |world| {
+            let almighty = SingleKey("alice".into());
+            let supply_all = std::fs::read("../target/wasm32-wasip2/debug/instruction.wasm")
+                .expect("component should have been built");
+
+            let upgrade = || {
+                instruction::InstructionEnum::from(
+                    instruction::BuiltinInstruction::UpgradeExecutable {
+                        executable: SingleKey("swap".into()),
+                        bytes: supply_all.clone(),
+                    },
+                )
+            };
+            let res = upgrade().execute(SingleKey("bob".into()), &mut world);
+            assert!(
+                matches!(res, Err(instruction::InstructionError::WriteRejected(_))),
+                "everyman should not upgrade executables: {res:?}"
+            );
+            let tx = transaction::Transaction {
+                authority: almighty.clone(),
+                instructions: vec![upgrade()],
+            };
+            tx.execute(&mut world).expect("almighty should upgrade");
+            assert_eq!(
+                world.executable[&SingleKey("swap".into())],
+                world.executable[&SingleKey("supply_all".into())],
+                "upgraded executable should take the new bytes"
+            );
+
+            instruction::InstructionEnum::from(
+                instruction::BuiltinInstruction::UnregisterExecutable {
+                    executable: SingleKey("swap".into()),
+                },
+            )
+            .execute(almighty.clone(), &mut world)
+            .expect("almighty should unregister");
+            let swap = instruction::InstructionEnum::Wasm(instruction::WasmInstruction {
+                executable: SingleKey("swap".into()),
+                args: serde_json::json!({ "from": "rose", "to": "tulip", "amount": 10 })
+                    .to_string(),
+                read_policy: instruction::ReadPolicy::default(),
+                budget: instruction::Budget::default(),
+            });
+            let res = swap.execute(almighty, &mut world);
+            assert!(
+                matches!(
+                    res,
+                    Err(instruction::InstructionError::ExecutableNotFound(_))
+                ),
+                "unregistered executable should not run: {res:?}"
+            );
+        });
+    }
+
+    #[test]
+    fn almighty_upgrades_authorizer_with_migration() {
+        on_every_backend!(with_grant(world(), "alice", "almighty"), |world| {
+            let almighty = SingleKey("alice".into());
+            let read = |guest: &str| {
+                std::fs::read(format!("../target/wasm32-wasip2/debug/{guest}.wasm"))
+                    .expect("component should have been built")
+            };
+            // A trailing custom section changes the hash but not the behavior
+            let mut upgraded = read("authorizer");
+            upgraded.extend([0x00, 0x05, 0x04, b'n', b'e', b'x', b't']);
+            let authorizer =
+                |world: &state::World| world.executable[&SingleKey(state::AUTHORIZER.into())].hash;
+            let original = authorizer(&world);
+
+            let auditor = FlexFuzzyTree(BTreeMap::from([(
+                // Any (account, asset) pair
+                FlexFuzzyNodeKey::AccountAsset(FlexFuzzyCompositeKey(None, None)),
+                NodeValue::AccountAsset(AccountAssetA {
+                    // Can read
+                    bit_mask: 0b0000_0001,
+                }),
+            )]));
+            let migration = BTreeMap::from([(
+                SingleKey("inspector".into()),
+                prelude::PermissionW::Redefine(PermissionV {
+                    permission: auditor.clone(),
+                }),
+            )]);
+            let upgrade = |bytes: &Vec<u8>, migration: &BTreeMap<_, _>| {
+                instruction::InstructionEnum::from(
+                    instruction::BuiltinInstruction::UpgradeAuthorizer {
+                        bytes: bytes.clone(),
+                        migration: migration.clone(),
+                    },
+                )
+            };
+
+            let res = upgrade(&upgraded, &migration).execute(SingleKey("bob".into()), &mut world);
+            assert!(
+                matches!(res, Err(instruction::InstructionError::WriteRejected(_))),
+                "everyman should not upgrade the authorizer: {res:?}"
+            );
+
+            let res = upgrade(&read("swap"), &migration).execute(almighty.clone(), &mut world);
+            assert!(
+                matches!(
+                    res,
+                    Err(instruction::InstructionError::StateViolation(
+                        state::WriteError::InvalidExecutable { .. }
+                    ))
+                ),
+                "instruction should not become the authorizer: {res:?}"
+            );
+
+            let mut broken = migration.clone();
+            broken.insert(SingleKey("everyman".into()), prelude::PermissionW::Delete);
+            let res = upgrade(&upgraded, &broken).execute(almighty.clone(), &mut world);
+            assert!(
+                matches!(
+                    res,
+                    Err(instruction::InstructionError::StateViolation(
+                        state::WriteError::PermissionInUse(_)
+                    ))
+                ),
+                "granted permission should not be deleted: {res:?}"
+            );
+            assert_eq!(
+                authorizer(&world),
+                original,
+                "failed migration should keep the authorizer"
+            );
+            assert_eq!(world.permission, *PERMISSION);
+
+            upgrade(&upgraded, &migration)
+                .execute(almighty.clone(), &mut world)
+                .expect("almighty should upgrade the authorizer");
+            assert_ne!(authorizer(&world), original);
+            assert_eq!(
+                world.permission[&SingleKey("inspector".into())],
+                PermissionV {
+                    permission: auditor
+                }
+            );
+
+            let res = instruction::InstructionEnum::from(
+                instruction::BuiltinInstruction::UnregisterExecutable {
+                    executable: SingleKey(state::AUTHORIZER.into()),
+                },
+            )
+            .execute(almighty.clone(), &mut world);
+            assert!(
+                matches!(
+                    res,
+                    Err(instruction::InstructionError::StateViolation(
+                        state::WriteError::AuthorizerRequired
+                    ))
+                ),
+                "authorizer should not be unregistered: {res:?}"
+            );
+
+            instruction::InstructionEnum::from(instruction::BuiltinInstruction::Mint {
+                account: SingleKey("bob".into()),
+                asset: "rose".into(),
+                amount: 10.into(),
+            })
+            .execute(almighty, &mut world)
+            .expect("upgraded authorizer should approve");
+        });
+    }
+
+    #[test]
+    fn simulation_previews_without_commit() {
+        let almighty = SingleKey("alice".into());
+        let mut world = with_grant(world(), "alice", "almighty");
+
+        let supply_all = |read_policy| {
+            instruction::InstructionEnum::Wasm(instruction::WasmInstruction {
+                executable: SingleKey("supply_all".to_string()),
+                args: serde_json::json!({
+                    "asset": "rose",
+                    "threshold": 100,
+                    "supply_amount": 50,
+                    "supplier": "bob"
+                })
+                .to_string(),
+                read_policy,
+                budget: instruction::Budget::default(),
+            })
+        };
+
+        let simulation = supply_all(instruction::ReadPolicy::Strict).simulate(almighty, &world);
+        let balance = |name: &str, balance: u128| {
             (
-                CompositeKey("carol".into(), "rose".into()),
-                AccountAssetV { balance: 90 },
+                NodeKey::AccountAsset(CompositeKey(name.into(), "rose".into())),
+                Some(NodeValue::AccountAsset(AccountAssetV {
+                    balance: balance.into(),
+                })),
+            )
+        };
+        let expected = state::StateDiff(BTreeMap::from([
+            balance("bob", 0),
+            balance("carol", 140),
+            balance("dave", 140),
+        ]));
+        assert_eq!(simulation.outcome.expect("should succeed"), expected);
+        assert_eq!(
+            simulation.view.expect("should read").0.len(),
+            4,
+            "every rose holder should be viewed"
+        );
+        let event = |name: &str, status| {
+            (
+                NodeKey::AccountAsset(CompositeKey(name.into(), "rose".into())),
+                NodeValue::AccountAsset(status),
+            )
+        };
+        let expected = prelude::Tree(BTreeMap::from([
+            event("bob", AccountAssetE::Send),
+            event("carol", AccountAssetE::Receive),
+            event("dave", AccountAssetE::Receive),
+        ]));
+        assert_eq!(simulation.events.expect("should request write"), expected);
+        let read_verdict = simulation.read_verdict.expect("should judge read");
+        assert!(read_verdict.rejected.is_empty());
+        let write_verdict = simulation.write_verdict.expect("should judge write");
+        assert!(write_verdict.rejected.is_empty());
+        assert_eq!(
+            world.account_asset, *ACCOUNT_ASSET,
+            "world should be untouched"
+        );
+
+        let everyman = SingleKey("dave".into());
+        let simulation = supply_all(instruction::ReadPolicy::Redact).simulate(everyman, &world);
+        let read_verdict = simulation.read_verdict.expect("should judge read");
+        assert!(
+            !read_verdict.rejected.is_empty(),
+            "redaction of others should be visible"
+        );
+        assert_eq!(
+            simulation.view.expect("should read").0.len(),
+            1,
+            "everyman should view only its own rose"
+        );
+
+        let inspector = SingleKey("carol".into());
+        world.account_permission.insert(
+            CompositeKey("carol".into(), "inspector".into()),
+            AccountPermissionV,
+        );
+        let simulation = supply_all(instruction::ReadPolicy::Strict).simulate(inspector, &world);
+        assert!(
+            simulation.events.is_some(),
+            "requirements should be previewed"
+        );
+        let write_verdict = simulation.write_verdict.expect("should judge write");
+        assert!(
+            !write_verdict.rejected.is_empty(),
+            "rejections should be previewed apart from the outcome"
+        );
+        assert!(
+            matches!(
+                simulation.outcome,
+                Err(instruction::InstructionError::WriteRejected(_))
             ),
+            "inspector should not supply from others: {:?}",
+            simulation.outcome
+        );
+        assert_eq!(
+            world.account_asset, *ACCOUNT_ASSET,
+            "world should be untouched"
+        );
+    }
+
+    #[test]
+    fn execution_leaves_serializable_record() {
+        on_every_backend!(with_grant(world(), "alice", "almighty"), |world| {
+            let almighty = SingleKey("alice".into());
+
+            let supply_all = instruction::InstructionEnum::Wasm(instruction::WasmInstruction {
+                executable: SingleKey("supply_all".to_string()),
+                args: serde_json::json!({
+                    "asset": "rose",
+                    "threshold": 100,
+                    "supply_amount": 50,
+                    "supplier": "bob"
+                })
+                .to_string(),
+                read_policy: instruction::ReadPolicy::default(),
+                budget: instruction::Budget::default(),
+            });
+            let record = supply_all
+                .clone()
+                .execute(almighty.clone(), &mut world)
+                .expect("should execute");
+
+            assert_eq!(record.authority, almighty);
+            assert_eq!(record.instruction, supply_all);
+            let hash = |key: &str| EXECUTABLE[&SingleKey(key.into())].hash;
+            assert_eq!(record.executable, Some(hash("supply_all")));
+            assert_eq!(record.authorizer, hash("authorizer"));
+            assert!(record.read_verdict.rejected.is_empty());
+            assert_eq!(record.view.0.len(), 4, "every rose holder should be viewed");
+            assert_eq!(record.events.0.len(), 3);
+            assert_eq!(record.diff.0.len(), 3);
+            assert!(record.spent.instruction.fuel > 0);
+            assert!(record.spent.authorizer.fuel > 0);
+            assert!(record.spent.instruction.memory > 0);
+
+            let archived = serde_json::to_string(&record).expect("should serialize");
+            let restored: instruction::Record =
+                serde_json::from_str(&archived).expect("should deserialize");
+            assert_eq!(restored, record);
+        });
+    }
+
+    #[test]
+    fn replay_reports_first_divergence() {
+        let almighty = SingleKey("alice".into());
+        let snapshot = || with_grant(world(), "alice", "almighty");
+
+        let supply_all = instruction::InstructionEnum::Wasm(instruction::WasmInstruction {
+            executable: SingleKey("supply_all".to_string()),
+            args: serde_json::json!({
+                "asset": "rose",
+                "threshold": 100,
+                "supply_amount": 50,
+                "supplier": "bob"
+            })
+            .to_string(),
+            read_policy: instruction::ReadPolicy::default(),
+            budget: instruction::Budget::default(),
+        });
+        let record = supply_all
+            .execute(almighty, &mut snapshot())
+            .expect("should execute");
+        let archived = serde_json::to_string(&record).expect("should serialize");
+        let record: instruction::Record =
+            serde_json::from_str(&archived).expect("should deserialize");
+
+        let world = snapshot();
+        record.replay(&world).expect("should replay as recorded");
+        assert_eq!(
+            world.account_asset, *ACCOUNT_ASSET,
+            "snapshot should be untouched"
+        );
+
+        let mut tampered = snapshot();
+        tampered
+            .account_asset
+            .get_mut(&CompositeKey("carol".into(), "rose".into()))
+            .expect("carol should hold rose")
+            .balance = 95.into();
+        let Err(instruction::Divergence::Mismatch { field, replayed }) = record.replay(&tampered)
+        else {
+            panic!("replay on another state should diverge");
+        };
+        assert_eq!(field, instruction::Field::View);
+        assert_eq!(replayed.read_request, record.read_request);
+
+        let mut upgraded = snapshot();
+        let mut bytes = std::fs::read("../target/wasm32-wasip2/debug/instruction.wasm")
+            .expect("component should have been built");
+        // Trailing custom section, which changes the hash but not the behavior
+        bytes.extend([0x00, 0x05, 0x04, b'n', b'e', b'x', b't']);
+        upgraded.executable.insert(
+            SingleKey("supply_all".into()),
+            ExecutableV::new(&ENGINE, &bytes).expect("component should compile"),
+        );
+        let Err(instruction::Divergence::Mismatch { field, .. }) = record.replay(&upgraded) else {
+            panic!("replay with another component should diverge");
+        };
+        assert_eq!(field, instruction::Field::Executable);
+    }
+
+    #[test]
+    fn storage_failure_fails_the_write() {
+        struct Broken;
+
+        impl storage::Storage for Broken {
+            fn append(&mut self, _diff: &state::StateDiff) -> std::io::Result<()> {
+                Err(std::io::Error::other("disk full"))
+            }
+
+            fn snapshot(&mut self, _world: &state::World) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let world = world();
+        let mut stored = storage::Stored::new(world, Broken);
+        let root = stored.root();
+
+        let transfer =
+            instruction::InstructionEnum::from(instruction::BuiltinInstruction::Transfer {
+                from: SingleKey("alice".into()),
+                to: SingleKey("bob".into()),
+                asset: "rose".into(),
+                amount: 200.into(),
+            });
+        let res = transfer
+            .clone()
+            .execute(SingleKey("alice".into()), &mut stored);
+        assert!(matches!(
+            res,
+            Err(instruction::InstructionError::Storage(_))
+        ));
+        assert_eq!(stored.account_asset, *ACCOUNT_ASSET);
+        assert_eq!(stored.root(), root);
+
+        let transaction = transaction::Transaction {
+            authority: SingleKey("alice".into()),
+            instructions: vec![transfer],
+        };
+        let err = transaction
+            .execute(&mut stored)
+            .expect_err("commit should fail");
+        assert_eq!(err.index, 1);
+        assert!(matches!(
+            *err.source,
+            instruction::InstructionError::Storage(_)
+        ));
+        assert_eq!(stored.account_asset, *ACCOUNT_ASSET);
+    }
+
+    #[test]
+    fn disk_recovers_from_snapshot_log_and_torn_write() {
+        let almighty = SingleKey("alice".into());
+        let genesis = with_grant(world(), "alice", "almighty");
+        let transfer = |amount: u128| {
+            instruction::InstructionEnum::from(instruction::BuiltinInstruction::Transfer {
+                from: SingleKey("alice".into()),
+                to: SingleKey("bob".into()),
+                asset: "rose".into(),
+                amount: amount.into(),
+            })
+        };
+        let swap = &EXECUTABLE[&SingleKey("swap".into())];
+
+        let dir = scratch("disk-recovers");
+        let disk = storage::Disk::create(&dir, &genesis)
+            .expect("should create store")
+            .snapshot_every(2);
+        let mut world = storage::Stored::new(genesis, disk);
+        // Snapshotted after the second commit, leaving the third in the log
+        transfer(10)
+            .execute(almighty.clone(), &mut world)
+            .expect("should transfer");
+        instruction::InstructionEnum::from(instruction::BuiltinInstruction::RegisterExecutable {
+            executable: SingleKey("swap_again".into()),
+            bytes: swap.bytes.to_vec(),
+        })
+        .execute(almighty.clone(), &mut world)
+        .expect("should register");
+        transfer(20)
+            .execute(almighty.clone(), &mut world)
+            .expect("should transfer");
+        let (before, _) = world.into_inner();
+        let log = std::fs::read_to_string(dir.join("log.jsonl")).expect("log should exist");
+        assert_eq!(
+            log.lines().count(),
+            1,
+            "earlier commits should be snapshotted"
+        );
+
+        // A crash in the middle of appending the next commit
+        let mut log = std::fs::OpenOptions::new()
+            .append(true)
+            .open(dir.join("log.jsonl"))
+            .expect("log should exist");
+        std::io::Write::write_all(&mut log, br#"{"seq":4,"diff":[[{"Acc"#).expect("should write");
+        drop(log);
+
+        let (disk, reopened) = storage::Disk::open(&dir, &ENGINE).expect("should reopen");
+        assert_eq!(reopened.account_asset, before.account_asset);
+        assert_eq!(reopened.executable, before.executable);
+        assert_eq!(
+            reopened.executable[&SingleKey("swap_again".into())].hash,
+            swap.hash
+        );
+
+        let mut world = storage::Stored::new(reopened, disk);
+        transfer(30)
+            .execute(almighty, &mut world)
+            .expect("should transfer after reopening");
+        let (before, _) = world.into_inner();
+        let (_, reopened) = storage::Disk::open(&dir, &ENGINE).expect("should reopen again");
+        assert_eq!(reopened.account_asset, before.account_asset);
+        assert_eq!(
+            reopened.account_asset[&CompositeKey("alice".into(), "rose".into())].balance,
+            (500 - 10 - 20 - 30).into()
+        );
+        std::fs::remove_dir_all(dir).expect("should clean up");
+    }
+
+    #[test]
+    fn torn_append_leaves_the_log_readable() {
+        let almighty = SingleKey("alice".into());
+        let genesis = with_grant(world(), "alice", "almighty");
+        let transfer = |amount: u128| {
+            instruction::InstructionEnum::from(instruction::BuiltinInstruction::Transfer {
+                from: SingleKey("alice".into()),
+                to: SingleKey("bob".into()),
+                asset: "rose".into(),
+                amount: amount.into(),
+            })
+        };
+
+        let dir = scratch("torn-append");
+        let mut disk = storage::Disk::create(&dir, &genesis).expect("should create store");
+        // A full disk takes only part of the first line
+        disk.tear_next_append(10);
+        let mut world = storage::Stored::new(genesis, disk);
+
+        let res = transfer(10).execute(almighty.clone(), &mut world);
+        assert!(
+            matches!(res, Err(instruction::InstructionError::Storage(_))),
+            "torn append should fail the write: {res:?}"
+        );
+        assert_eq!(world.account_asset, *ACCOUNT_ASSET);
+        transfer(20)
+            .execute(almighty, &mut world)
+            .expect("should transfer after the failed append");
+
+        let (before, _) = world.into_inner();
+        let (_, reopened) = storage::Disk::open(&dir, &ENGINE).expect("should reopen");
+        assert_eq!(reopened.account_asset, before.account_asset);
+        assert_eq!(
+            reopened.account_asset[&CompositeKey("alice".into(), "rose".into())].balance,
+            (500 - 20).into()
+        );
+        std::fs::remove_dir_all(dir).expect("should clean up");
+    }
+
+    #[test]
+    fn state_root_tracks_commits_and_proves_reads() {
+        let almighty = SingleKey("alice".into());
+        let world = with_grant(world(), "alice", "almighty");
+        let mut world = storage::Stored::new(world, storage::Memory);
+        let genesis = world.root();
+
+        instruction::InstructionEnum::from(instruction::BuiltinInstruction::Transfer {
+            from: SingleKey("alice".into()),
+            to: SingleKey("bob".into()),
+            asset: "rose".into(),
+            amount: 200.into(),
+        })
+        .execute(almighty, &mut world)
+        .expect("should transfer");
+        let root = world.root();
+        assert_ne!(root, genesis);
+        assert_eq!(
+            root,
+            commitment::StateTree::new(&world).root(),
+            "incremental root should match the one from scratch"
+        );
+
+        let (view, proofs) = world.read_with_proofs(&prelude::FuzzyTree(BTreeMap::from([(
+            FuzzyNodeKey::AccountAsset(FuzzyCompositeKey(None, Some("rose".into()))),
+            NodeValue::AccountAsset(AccountAssetR),
+        )])));
+        assert_eq!(proofs.len(), view.0.len());
+        for (key, value) in view.0 {
+            let value = value.into();
+            assert!(proofs[&key].verify(&root, &key, Some(&value)));
+            assert!(!proofs[&key].verify(&root, &key, None));
+        }
+
+        let alice = NodeKey::AccountAsset(CompositeKey("alice".into(), "rose".into()));
+        let stale = NodeValue::AccountAsset(AccountAssetV {
+            balance: 500.into(),
+        });
+        let proof = &proofs[&alice];
+        assert!(
+            !proof.verify(&root, &alice, Some(&stale)),
+            "stale balance should not verify"
+        );
+
+        let frank = NodeKey::AccountAsset(CompositeKey("frank".into(), "rose".into()));
+        let proof = world.tree().prove(&frank);
+        assert!(proof.verify(&root, &frank, None), "absence should verify");
+
+        let bob = NodeKey::Account(SingleKey("bob".into()));
+        let proof = world.tree().prove(&bob);
+        assert!(proof.verify(&root, &bob, Some(&NodeValue::Account(AccountV))));
+        let rose = NodeKey::AssetDefinition(SingleKey("rose".into()));
+        let proof = world.tree().prove(&rose);
+        let definition =
+            NodeValue::AssetDefinition(ASSET_DEFINITION[&SingleKey("rose".into())].clone());
+        assert!(proof.verify(&root, &rose, Some(&definition)));
+
+        // Callers generic over the state get proofs wherever a state tree is kept
+        fn proves(
+            world: &impl state::WorldState,
+            key: &NodeKey,
+            value: &NodeValue<prelude::Archive>,
+        ) -> Option<bool> {
+            Some(
+                world
+                    .prove(key)?
+                    .verify(&world.state_root()?, key, Some(value)),
+            )
+        }
+        let value = NodeValue::Account(AccountV);
+        assert_eq!(proves(&world, &bob, &value), Some(true));
+        assert_eq!(proves(&*world, &bob, &value), None);
+    }
+
+    #[test]
+    fn genesis_builds_the_fixture_world() {
+        let world =
+            genesis::Genesis::load("fixtures/genesis.json", &ENGINE).expect("should load genesis");
+        assert_eq!(world.context, Context::default());
+        assert_eq!(world.executable, *EXECUTABLE);
+        assert_eq!(world.permission, *PERMISSION);
+        assert_eq!(world.account, *ACCOUNT);
+        assert_eq!(world.asset_definition, *ASSET_DEFINITION);
+        assert_eq!(world.account_asset, *ACCOUNT_ASSET);
+        assert_eq!(world.account_permission, *ACCOUNT_PERMISSION);
+        world.audit_supply().expect("supply should add up");
+
+        let fixture: genesis::Genesis = serde_json::from_slice(
+            &std::fs::read("fixtures/genesis.json").expect("fixture should exist"),
+        )
+        .expect("fixture should parse");
+        let build =
+            |genesis: genesis::Genesis| genesis.build(std::path::Path::new("fixtures"), &ENGINE);
+
+        let mut genesis = fixture.clone();
+        genesis
+            .roles
+            .insert("frank".into(), ["everyman".into()].into());
+        let Err(genesis::GenesisError::NoAccount(account)) = build(genesis) else {
+            panic!("grantee should be an account");
+        };
+        assert_eq!(account, SingleKey("frank".into()));
+
+        let mut genesis = fixture.clone();
+        genesis
+            .roles
+            .insert("alice".into(), ["overlord".into()].into());
+        let Err(genesis::GenesisError::NoPermission(permission)) = build(genesis) else {
+            panic!("role should be a defined permission");
+        };
+        assert_eq!(permission, SingleKey("overlord".into()));
+
+        let mut genesis = fixture;
+        let authorizer = genesis.authorizer.clone();
+        genesis
+            .executables
+            .insert(state::AUTHORIZER.into(), authorizer);
+        let Err(genesis::GenesisError::ReservedExecutable(_)) = build(genesis) else {
+            panic!("authorizer key should be reserved");
+        };
+    }
+
+    #[test]
+    fn genesis_rejects_invalid_allowances() {
+        let res = genesis::Genesis::load("fixtures/genesis_mismatched_allowance.json", &ENGINE);
+        let Err(genesis::GenesisError::MismatchedAllowance { permission, key }) = res else {
+            panic!("allowance should fit its key");
+        };
+        assert_eq!(permission, SingleKey("everyman".into()));
+        assert_eq!(key, FlexFuzzyNodeKey::Account(FlexFuzzySingleKey(None)));
+
+        let mut genesis: genesis::Genesis = serde_json::from_slice(
+            &std::fs::read("fixtures/genesis.json").expect("fixture should exist"),
+        )
+        .expect("fixture should parse");
+        let inspector = genesis
+            .permissions
+            .get_mut("inspector")
+            .expect("fixture should define inspector");
+        let key = FlexFuzzyNodeKey::AccountAsset(FlexFuzzyCompositeKey(None, None));
+        inspector.0.insert(
+            key.clone(),
+            NodeValue::AccountAsset(AccountAssetA {
+                bit_mask: 0b0000_1001,
+            }),
+        );
+        let res = genesis.build(std::path::Path::new("fixtures"), &ENGINE);
+        let Err(genesis::GenesisError::UndefinedBits { bits, .. }) = res else {
+            panic!("undefined bits should be rejected");
+        };
+        assert_eq!(bits, 0b0000_1000);
+    }
+
+    #[test]
+    fn invalid_allowances_are_not_defined() {
+        on_every_backend!(with_grant(world(), "alice", "almighty"), |world| {
+            let almighty = SingleKey("alice".into());
+            let define = |allow| {
+                instruction::InstructionEnum::from(
+                    instruction::BuiltinInstruction::DefinePermission {
+                        permission: SingleKey("malformed".into()),
+                        allow: FlexFuzzyTree(BTreeMap::from([allow])),
+                    },
+                )
+            };
+            let key = FlexFuzzyNodeKey::AccountAsset(FlexFuzzyCompositeKey(None, None));
+
+            let res = define((
+                key.clone(),
+                NodeValue::Account(AccountA {
+                    bit_mask: 0b0000_0001,
+                }),
+            ))
+            .execute(almighty.clone(), &mut world);
+            assert!(
+                matches!(
+                    res,
+                    Err(instruction::InstructionError::StateViolation(
+                        state::WriteError::MismatchedAllowance { .. }
+                    ))
+                ),
+                "allowance should fit its key: {res:?}"
+            );
+
+            let res = define((
+                key,
+                NodeValue::AccountAsset(AccountAssetA {
+                    bit_mask: 0b0000_1001,
+                }),
+            ))
+            .execute(almighty, &mut world);
+            assert!(
+                matches!(
+                    res,
+                    Err(instruction::InstructionError::StateViolation(
+                        state::WriteError::UndefinedBits {
+                            bits: 0b0000_1000,
+                            ..
+                        }
+                    ))
+                ),
+                "undefined bits should be rejected: {res:?}"
+            );
+            assert!(
+                !world
+                    .permission
+                    .contains_key(&SingleKey("malformed".into()))
+            );
+        });
+    }
+
+    #[test]
+    fn json_encoding_is_stable() {
+        fn json(value: &impl serde::Serialize) -> String {
+            serde_json::to_string(value).expect("should serialize")
+        }
+
+        assert_eq!(
+            json(&NodeKey::AccountAsset(CompositeKey(
+                "alice".into(),
+                "rose".into()
+            ))),
+            r#"{"AccountAsset":["alice","rose"]}"#
+        );
+        assert_eq!(
+            json(&FlexFuzzyNodeKey::AccountAsset(FlexFuzzyCompositeKey(
+                Some(FlexKeyElem::This),
+                None
+            ))),
+            r#"{"AccountAsset":["This",null]}"#
+        );
+        let transfer: prelude::WriteSet = prelude::FlexTree(BTreeMap::from([(
+            prelude::FlexNodeKey::AccountAsset(prelude::FlexCompositeKey(
+                FlexKeyElem::That("bob".into()),
+                "rose".into(),
+            )),
+            NodeValue::AccountAsset(prelude::AccountAssetW::Send(Numeric::new(5, 2))),
+        )]));
+        assert_eq!(
+            json(&transfer),
+            r#"[[{"AccountAsset":[{"That":"bob"},"rose"]},{"AccountAsset":{"Send":{"mantissa":5,"scale":2}}}]]"#
+        );
+        let events: prelude::EventSet = prelude::Tree(BTreeMap::from([(
+            NodeKey::AccountAsset(CompositeKey("bob".into(), "rose".into())),
+            NodeValue::AccountAsset(AccountAssetE::Send),
+        )]));
+        assert_eq!(
+            json(&events),
+            r#"[[{"AccountAsset":["bob","rose"]},{"AccountAsset":"Send"}]]"#
+        );
+
+        let transaction = transaction::Transaction {
+            authority: SingleKey("alice".into()),
+            instructions: vec![instruction::InstructionEnum::from(
+                instruction::BuiltinInstruction::Transfer {
+                    from: SingleKey("alice".into()),
+                    to: SingleKey("bob".into()),
+                    asset: "rose".into(),
+                    amount: 200.into(),
+                },
+            )],
+        };
+        let exchanged = serde_json::to_string(&transaction).expect("should serialize");
+        assert_eq!(
+            serde_json::from_str::<transaction::Transaction>(&exchanged)
+                .expect("should deserialize"),
+            transaction
+        );
+
+        let world = world();
+        let dump =
+            serde_json::to_string(&state::ArchivedWorld::from(&world)).expect("should export");
+        let archived: state::ArchivedWorld = serde_json::from_str(&dump).expect("should import");
+        let by_hash = |hash| {
+            EXECUTABLE
+                .values()
+                .find(|executable| executable.hash == hash)
+                .cloned()
+                .ok_or(hash)
+        };
+        let restored = archived.restore(by_hash).expect("should restore");
+        assert_eq!(
+            state::ArchivedWorld::from(&restored),
+            state::ArchivedWorld::from(&world)
+        );
+        assert_eq!(restored.executable, world.executable);
+
+        let authorizer = NodeKey::Executable(SingleKey("authorizer".into()));
+        let view = state::WorldState::read(
+            &world,
+            &prelude::FuzzyTree(BTreeMap::from([(
+                FuzzyNodeKey::Executable(FuzzySingleKey(None)),
+                NodeValue::Executable(prelude::ExecutableR),
+            )])),
+        );
+        assert_eq!(
+            json(&view),
+            json(&prelude::Tree::<prelude::Archive>::from(view.clone())),
+            "views should encode executables by hash"
+        );
+        let diff = state::StateDiff(BTreeMap::from([
             (
-                CompositeKey("dave".into(), "rose".into()),
-                AccountAssetV { balance: 90 },
+                authorizer,
+                Some(view.0.into_values().next().expect("should view")),
             ),
             (
-                CompositeKey("eve".into(), "tulip".into()),
-                AccountAssetV { balance: 90 },
+                NodeKey::Account(SingleKey("frank".into())),
+                Some(NodeValue::Account(AccountV)),
             ),
-        ];
-
-        assert_eq!(world.account_asset, expected.into());
+            (NodeKey::Account(SingleKey("eve".into())), None),
+        ]));
+        assert_eq!(
+            json(&diff),
+            json(&state::ArchivedDiff::from(diff.clone())),
+            "diffs should encode executables by hash"
+        );
     }
 }
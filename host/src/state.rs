@@ -1,50 +1,98 @@
 use std::collections::BTreeMap;
 
+use derive_more::{Display, Error};
+
+use crate::instruction::InstructionError;
 use crate::prelude::*;
 
+/// Key of the executable that authorizes every instruction.
+///
+/// Kept among the executables so that replacing it goes through the same authorized writes.
+pub const AUTHORIZER: &str = "authorizer";
+
 pub trait WorldState {
-    fn authorizer(&self) -> &crate::instruction::WasmComponent;
+    fn context(&self) -> Context;
     fn executable(&self, executable: &ExecutableK) -> Option<&ExecutableV>;
-    fn permission(&self, authority: &AccountK) -> AllowSet;
     fn read(&self, request: &ReadSet) -> ViewSet;
-    fn write(&mut self, request: &WriteSet, authority: AccountK);
-}
+    /// Applies a diff that has already been validated by [`WorldState::stage`].
+    ///
+    /// Fails only if the diff cannot be made durable, in which case it does not take effect.
+    fn commit(&mut self, diff: StateDiff) -> std::io::Result<()>;
 
-pub struct World {
-    pub authorizer: crate::instruction::WasmComponent,
-    pub executable: BTreeMap<ExecutableK, ExecutableV>,
-    pub permission: BTreeMap<PermissionK, PermissionV>,
-    pub account_asset: BTreeMap<AccountAssetK, AccountAssetV>,
-    pub account_permission: BTreeMap<AccountPermissionK, ()>,
-}
+    /// Root of the state tree as of the last commit, if the world keeps one.
+    fn state_root(&self) -> Option<Hash> {
+        None
+    }
 
-impl WorldState for World {
-    fn authorizer(&self) -> &crate::instruction::WasmComponent {
-        &self.authorizer
+    /// Proves the entry at `key`, or its absence, against [`WorldState::state_root`], if the world
+    /// keeps a state tree.
+    fn prove(&self, _key: &NodeKey) -> Option<crate::commitment::Proof> {
+        None
     }
 
-    fn executable(&self, executable: &ExecutableK) -> Option<&ExecutableV> {
-        self.executable.get(executable)
+    /// The executable registered under [`AUTHORIZER`], which the world never goes without.
+    fn authorizer(&self) -> &ExecutableV {
+        self.executable(&SingleKey(AUTHORIZER.into()))
+            .expect("world should have an authorizer")
     }
 
+    /// Unites the allowances of every permission granted to the authority.
     fn permission(&self, authority: &AccountK) -> AllowSet {
-        let permission_keys: Vec<_> = self
-            .account_permission
-            .keys()
-            .filter_map(|key| (key.0 == authority.0).then_some(key.1.clone()))
-            .collect();
+        let granted = self.read(&FuzzyTree(BTreeMap::from([(
+            FuzzyNodeKey::AccountPermission(FuzzyCompositeKey(Some(authority.0.clone()), None)),
+            NodeValue::AccountPermission(AccountPermissionR),
+        )])));
+        let request = FuzzyTree(
+            granted
+                .0
+                .into_keys()
+                .filter_map(|key| match key {
+                    NodeKey::AccountPermission(CompositeKey(_, permission)) => Some((
+                        FuzzyNodeKey::Permission(FuzzySingleKey(Some(permission))),
+                        NodeValue::Permission(PermissionR),
+                    )),
+                    _ => None,
+                })
+                .collect(),
+        );
         let permission_union = self
-            .permission
-            .iter()
-            .filter(|(k, _)| permission_keys.contains(&k.0))
-            .map(|(_, v)| v.permission.0.clone())
+            .read(&request)
+            .0
+            .into_values()
+            .filter_map(|value| match value {
+                NodeValue::Permission(PermissionV { permission }) => Some(permission.0),
+                _ => None,
+            })
             .fold(BTreeMap::new(), |mut acc, curr| {
                 for (k, v) in curr {
                     acc.entry(k)
-                        .and_modify(|e| {
-                            let NodeValue::AccountAsset(AccountAssetA { bit_mask: acc }) = e;
-                            let NodeValue::AccountAsset(AccountAssetA { bit_mask: curr }) = v;
-                            *acc |= curr;
+                        .and_modify(|e| match (e, &v) {
+                            (
+                                NodeValue::Account(AccountA { bit_mask: acc }),
+                                NodeValue::Account(AccountA { bit_mask: curr }),
+                            )
+                            | (
+                                NodeValue::AssetDefinition(AssetDefinitionA { bit_mask: acc }),
+                                NodeValue::AssetDefinition(AssetDefinitionA { bit_mask: curr }),
+                            )
+                            | (
+                                NodeValue::AccountAsset(AccountAssetA { bit_mask: acc }),
+                                NodeValue::AccountAsset(AccountAssetA { bit_mask: curr }),
+                            )
+                            | (
+                                NodeValue::Permission(PermissionA { bit_mask: acc }),
+                                NodeValue::Permission(PermissionA { bit_mask: curr }),
+                            )
+                            | (
+                                NodeValue::AccountPermission(AccountPermissionA { bit_mask: acc }),
+                                NodeValue::AccountPermission(AccountPermissionA { bit_mask: curr }),
+                            )
+                            | (
+                                NodeValue::Executable(ExecutableA { bit_mask: acc }),
+                                NodeValue::Executable(ExecutableA { bit_mask: curr }),
+                            ) => *acc |= curr,
+                            // A key never holds a value of another node type
+                            _ => {}
                         })
                         .or_insert(v);
                 }
@@ -54,74 +102,821 @@ impl WorldState for World {
         FlexFuzzyTree(permission_union)
     }
 
+    /// Validates the request against the current state and computes the resulting diff.
+    ///
+    /// Entries apply in key order, so accounts and asset definitions are registered or
+    /// unregistered before their balances are touched within the same request, and permissions
+    /// are defined before they are granted. Mints and burns carry over to the total supply of the
+    /// asset.
+    fn stage(&self, request: &WriteSet, authority: &AccountK) -> Result<StateDiff, WriteError> {
+        let request = request.clone().resolve(authority.0.clone());
+        let touched = FuzzyTree(
+            request
+                .0
+                .keys()
+                .flat_map(|key| match key {
+                    NodeKey::Account(SingleKey(e0)) => vec![
+                        (
+                            FuzzyNodeKey::Account(FuzzySingleKey(Some(e0.clone()))),
+                            NodeValue::Account(AccountR),
+                        ),
+                        // Holdings to check on unregistration
+                        (
+                            FuzzyNodeKey::AccountAsset(FuzzyCompositeKey(Some(e0.clone()), None)),
+                            NodeValue::AccountAsset(AccountAssetR),
+                        ),
+                        // Grants to drop on unregistration
+                        (
+                            FuzzyNodeKey::AccountPermission(FuzzyCompositeKey(
+                                Some(e0.clone()),
+                                None,
+                            )),
+                            NodeValue::AccountPermission(AccountPermissionR),
+                        ),
+                    ],
+                    NodeKey::AssetDefinition(SingleKey(e0)) => vec![
+                        (
+                            FuzzyNodeKey::AssetDefinition(FuzzySingleKey(Some(e0.clone()))),
+                            NodeValue::AssetDefinition(AssetDefinitionR),
+                        ),
+                        // Holdings to drop on unregistration
+                        (
+                            FuzzyNodeKey::AccountAsset(FuzzyCompositeKey(None, Some(e0.clone()))),
+                            NodeValue::AccountAsset(AccountAssetR),
+                        ),
+                    ],
+                    NodeKey::AccountAsset(CompositeKey(e0, e1)) => vec![
+                        (
+                            FuzzyNodeKey::Account(FuzzySingleKey(Some(e0.clone()))),
+                            NodeValue::Account(AccountR),
+                        ),
+                        (
+                            FuzzyNodeKey::AssetDefinition(FuzzySingleKey(Some(e1.clone()))),
+                            NodeValue::AssetDefinition(AssetDefinitionR),
+                        ),
+                        (
+                            FuzzyNodeKey::AccountAsset(FuzzyCompositeKey(
+                                Some(e0.clone()),
+                                Some(e1.clone()),
+                            )),
+                            NodeValue::AccountAsset(AccountAssetR),
+                        ),
+                    ],
+                    NodeKey::Permission(SingleKey(e0)) => vec![
+                        (
+                            FuzzyNodeKey::Permission(FuzzySingleKey(Some(e0.clone()))),
+                            NodeValue::Permission(PermissionR),
+                        ),
+                        // Grants to check on deletion
+                        (
+                            FuzzyNodeKey::AccountPermission(FuzzyCompositeKey(
+                                None,
+                                Some(e0.clone()),
+                            )),
+                            NodeValue::AccountPermission(AccountPermissionR),
+                        ),
+                    ],
+                    NodeKey::AccountPermission(CompositeKey(e0, e1)) => vec![
+                        (
+                            FuzzyNodeKey::Account(FuzzySingleKey(Some(e0.clone()))),
+                            NodeValue::Account(AccountR),
+                        ),
+                        (
+                            FuzzyNodeKey::Permission(FuzzySingleKey(Some(e1.clone()))),
+                            NodeValue::Permission(PermissionR),
+                        ),
+                        (
+                            FuzzyNodeKey::AccountPermission(FuzzyCompositeKey(
+                                Some(e0.clone()),
+                                Some(e1.clone()),
+                            )),
+                            NodeValue::AccountPermission(AccountPermissionR),
+                        ),
+                    ],
+                    NodeKey::Executable(SingleKey(e0)) => vec![(
+                        FuzzyNodeKey::Executable(FuzzySingleKey(Some(e0.clone()))),
+                        NodeValue::Executable(ExecutableR),
+                    )],
+                })
+                .collect(),
+        );
+        let current = self.read(&touched);
+
+        // Validate every entry against the staged state before touching the world
+        let mut staged = StateDiff::default();
+        let get = |staged: &StateDiff, key: &NodeKey| match staged.0.get(key) {
+            Some(value) => value.clone(),
+            None => current.0.get(key).cloned(),
+        };
+        // Executables run on the same engine as the authorizer
+        let compile = |executable: &ExecutableK, bytes: &[u8]| {
+            let invalid = |reason: String| WriteError::InvalidExecutable {
+                executable: executable.clone(),
+                reason,
+            };
+            let compiled = ExecutableV::new(self.authorizer().component.engine(), bytes)
+                .map_err(|error| invalid(format!("{error:#}")))?;
+            if executable.0 == AUTHORIZER {
+                crate::instruction::validate_authorizer(&compiled.component)
+            } else {
+                crate::instruction::validate_instruction(&compiled.component)
+            }
+            .map_err(|error| invalid(error.to_string()))?;
+
+            Ok(compiled)
+        };
+        for (key, value) in request.0 {
+            match (&key, value) {
+                (NodeKey::Account(account), NodeValue::Account(AccountW::Register)) => {
+                    if get(&staged, &key).is_some() {
+                        return Err(WriteError::AccountExists(account.clone()));
+                    }
+                    staged.0.insert(key, Some(NodeValue::Account(AccountV)));
+                }
+                (NodeKey::Account(account), NodeValue::Account(AccountW::Unregister)) => {
+                    if get(&staged, &key).is_none() {
+                        return Err(WriteError::NoAccount(account.clone()));
+                    }
+                    let holdings: Vec<_> = current
+                        .0
+                        .keys()
+                        .filter(|k| matches!(k, NodeKey::AccountAsset(k) if k.0 == account.0))
+                        .cloned()
+                        .collect();
+                    for holding in holdings {
+                        if let Some(NodeValue::AccountAsset(AccountAssetV { balance })) =
+                            get(&staged, &holding)
+                            && !balance.is_zero()
+                        {
+                            return Err(WriteError::AccountNotEmpty(account.clone()));
+                        }
+                        // Empty balances go away with the account
+                        staged.0.insert(holding, None);
+                    }
+                    // And so do its grants
+                    let grants: Vec<_> = current
+                        .0
+                        .keys()
+                        .filter(|k| matches!(k, NodeKey::AccountPermission(k) if k.0 == account.0))
+                        .cloned()
+                        .collect();
+                    for grant in grants {
+                        staged.0.insert(grant, None);
+                    }
+                    staged.0.insert(key, None);
+                }
+                (
+                    NodeKey::AssetDefinition(asset),
+                    NodeValue::AssetDefinition(AssetDefinitionW::Register { scale }),
+                ) => {
+                    if get(&staged, &key).is_some() {
+                        return Err(WriteError::AssetDefinitionExists(asset.clone()));
+                    }
+                    let definition = AssetDefinitionV {
+                        owner: authority.clone(),
+                        scale,
+                        total_supply: Numeric::new(0, scale),
+                    };
+                    staged
+                        .0
+                        .insert(key, Some(NodeValue::AssetDefinition(definition)));
+                }
+                (
+                    NodeKey::AssetDefinition(asset),
+                    NodeValue::AssetDefinition(AssetDefinitionW::Unregister),
+                ) => {
+                    let Some(NodeValue::AssetDefinition(definition)) = get(&staged, &key) else {
+                        return Err(WriteError::NoAssetDefinition(asset.clone()));
+                    };
+                    if !definition.total_supply.is_zero() {
+                        return Err(WriteError::AssetInCirculation(asset.clone()));
+                    }
+                    // With no supply left, every remaining balance is empty
+                    let holdings: Vec<_> = current
+                        .0
+                        .keys()
+                        .filter(|k| matches!(k, NodeKey::AccountAsset(k) if k.1 == asset.0))
+                        .cloned()
+                        .collect();
+                    for holding in holdings {
+                        staged.0.insert(holding, None);
+                    }
+                    staged.0.insert(key, None);
+                }
+                (NodeKey::AccountAsset(CompositeKey(e0, e1)), NodeValue::AccountAsset(v)) => {
+                    let account = SingleKey(e0.clone());
+                    if get(&staged, &NodeKey::Account(account.clone())).is_none() {
+                        return Err(WriteError::NoAccount(account));
+                    }
+                    let asset = SingleKey(e1.clone());
+                    let asset_key = NodeKey::AssetDefinition(asset.clone());
+                    let Some(NodeValue::AssetDefinition(mut definition)) = get(&staged, &asset_key)
+                    else {
+                        return Err(WriteError::NoAssetDefinition(asset));
+                    };
+                    // Amounts are kept at the scale of the asset
+                    let (AccountAssetW::Send(amount)
+                    | AccountAssetW::Receive(amount)
+                    | AccountAssetW::Mint(amount)
+                    | AccountAssetW::Burn(amount)) = v;
+                    let amount = amount.rescale(definition.scale).ok_or(
+                        WriteError::UnrepresentableAmount {
+                            asset: asset.clone(),
+                            amount,
+                            scale: definition.scale,
+                        },
+                    )?;
+                    let current = match get(&staged, &key) {
+                        Some(NodeValue::AccountAsset(AccountAssetV { balance })) => Some(balance),
+                        _ => None,
+                    };
+                    let balance = match v {
+                        AccountAssetW::Receive(_) | AccountAssetW::Mint(_) => current
+                            .unwrap_or(Numeric::new(0, definition.scale))
+                            .checked_add(amount)
+                            .ok_or(WriteError::BalanceOverflow(key.clone()))?,
+                        AccountAssetW::Send(_) | AccountAssetW::Burn(_) => {
+                            let Some(balance) = current else {
+                                return Err(WriteError::NoBalance(key));
+                            };
+                            balance
+                                .checked_sub(amount)
+                                .ok_or(WriteError::InsufficientBalance {
+                                    key: CompositeKey(e0.clone(), e1.clone()),
+                                    balance,
+                                    amount,
+                                })?
+                        }
+                    };
+                    let total_supply = match v {
+                        AccountAssetW::Mint(_) => Some(
+                            definition
+                                .total_supply
+                                .checked_add(amount)
+                                .ok_or(WriteError::SupplyOverflow(asset))?,
+                        ),
+                        // Underflows only if the balances exceed the supply they add up to
+                        AccountAssetW::Burn(_) => {
+                            Some(definition.total_supply.checked_sub(amount).ok_or(
+                                WriteError::SupplyUnderflow {
+                                    asset,
+                                    total_supply: definition.total_supply,
+                                    amount,
+                                },
+                            )?)
+                        }
+                        AccountAssetW::Send(_) | AccountAssetW::Receive(_) => None,
+                    };
+                    staged.0.insert(
+                        key,
+                        Some(NodeValue::AccountAsset(AccountAssetV { balance })),
+                    );
+                    if let Some(total_supply) = total_supply {
+                        definition.total_supply = total_supply;
+                        staged
+                            .0
+                            .insert(asset_key, Some(NodeValue::AssetDefinition(definition)));
+                    }
+                }
+                (
+                    NodeKey::Permission(permission),
+                    NodeValue::Permission(PermissionW::Define(definition)),
+                ) => {
+                    if get(&staged, &key).is_some() {
+                        return Err(WriteError::PermissionExists(permission.clone()));
+                    }
+                    check_allowances(permission, &definition.permission)?;
+                    staged
+                        .0
+                        .insert(key, Some(NodeValue::Permission(definition)));
+                }
+                (
+                    NodeKey::Permission(permission),
+                    NodeValue::Permission(PermissionW::Redefine(definition)),
+                ) => {
+                    if get(&staged, &key).is_none() {
+                        return Err(WriteError::NoPermission(permission.clone()));
+                    }
+                    check_allowances(permission, &definition.permission)?;
+                    staged
+                        .0
+                        .insert(key, Some(NodeValue::Permission(definition)));
+                }
+                (NodeKey::Permission(permission), NodeValue::Permission(PermissionW::Delete)) => {
+                    if get(&staged, &key).is_none() {
+                        return Err(WriteError::NoPermission(permission.clone()));
+                    }
+                    let in_use = current
+                        .0
+                        .keys()
+                        .chain(staged.0.keys())
+                        .filter(
+                            |k| matches!(k, NodeKey::AccountPermission(k) if k.1 == permission.0),
+                        )
+                        .any(|grant| get(&staged, grant).is_some());
+                    if in_use {
+                        return Err(WriteError::PermissionInUse(permission.clone()));
+                    }
+                    staged.0.insert(key, None);
+                }
+                (
+                    NodeKey::AccountPermission(CompositeKey(e0, e1)),
+                    NodeValue::AccountPermission(AccountPermissionW::Grant),
+                ) => {
+                    let account = SingleKey(e0.clone());
+                    if get(&staged, &NodeKey::Account(account.clone())).is_none() {
+                        return Err(WriteError::NoAccount(account));
+                    }
+                    let permission = SingleKey(e1.clone());
+                    if get(&staged, &NodeKey::Permission(permission.clone())).is_none() {
+                        return Err(WriteError::NoPermission(permission));
+                    }
+                    if get(&staged, &key).is_some() {
+                        return Err(WriteError::AlreadyGranted(key));
+                    }
+                    staged
+                        .0
+                        .insert(key, Some(NodeValue::AccountPermission(AccountPermissionV)));
+                }
+                (
+                    NodeKey::AccountPermission(_),
+                    NodeValue::AccountPermission(AccountPermissionW::Revoke),
+                ) => {
+                    if get(&staged, &key).is_none() {
+                        return Err(WriteError::NotGranted(key));
+                    }
+                    staged.0.insert(key, None);
+                }
+                (
+                    NodeKey::Executable(executable),
+                    NodeValue::Executable(ExecutableW::Register(bytes)),
+                ) => {
+                    if get(&staged, &key).is_some() {
+                        return Err(WriteError::ExecutableExists(executable.clone()));
+                    }
+                    let compiled = compile(executable, &bytes)?;
+                    staged.0.insert(key, Some(NodeValue::Executable(compiled)));
+                }
+                (
+                    NodeKey::Executable(executable),
+                    NodeValue::Executable(ExecutableW::Upgrade(bytes)),
+                ) => {
+                    if get(&staged, &key).is_none() {
+                        return Err(WriteError::NoExecutable(executable.clone()));
+                    }
+                    let compiled = compile(executable, &bytes)?;
+                    staged.0.insert(key, Some(NodeValue::Executable(compiled)));
+                }
+                (
+                    NodeKey::Executable(executable),
+                    NodeValue::Executable(ExecutableW::Unregister),
+                ) => {
+                    if executable.0 == AUTHORIZER {
+                        return Err(WriteError::AuthorizerRequired);
+                    }
+                    if get(&staged, &key).is_none() {
+                        return Err(WriteError::NoExecutable(executable.clone()));
+                    }
+                    staged.0.insert(key, None);
+                }
+                _ => return Err(WriteError::MismatchedValue(key)),
+            }
+        }
+
+        Ok(staged)
+    }
+
+    /// Applies the whole request or, if any entry is invalid, nothing.
+    fn write(&mut self, request: &WriteSet, authority: AccountK) -> Result<(), InstructionError> {
+        let diff = self
+            .stage(request, &authority)
+            .map_err(InstructionError::StateViolation)?;
+
+        self.commit(diff).map_err(InstructionError::Storage)
+    }
+}
+
+/// Post-commit values of the nodes touched by a write, where `None` removes the node.
+///
+/// Serializes the same as its [`ArchivedDiff`], which is what deserializes.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct StateDiff(
+    #[serde(serialize_with = "entries::serialize")] pub BTreeMap<NodeKey, Option<NodeValue<View>>>,
+);
+
+/// A [`StateDiff`] as archived, where executables are known by their hash alone.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ArchivedDiff(
+    #[serde(with = "entries")] pub BTreeMap<NodeKey, Option<NodeValue<Archive>>>,
+);
+
+impl From<StateDiff> for ArchivedDiff {
+    fn from(diff: StateDiff) -> Self {
+        Self(
+            diff.0
+                .into_iter()
+                .map(|(key, value)| (key, value.map(Into::into)))
+                .collect(),
+        )
+    }
+}
+
+/// A [`World`] as exported, where executables are known by their hash alone.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ArchivedWorld {
+    pub context: Context,
+    #[serde(with = "entries")]
+    pub executable: BTreeMap<ExecutableK, Hash>,
+    #[serde(with = "entries")]
+    pub permission: BTreeMap<PermissionK, PermissionV>,
+    #[serde(with = "entries")]
+    pub account: BTreeMap<AccountK, AccountV>,
+    #[serde(with = "entries")]
+    pub asset_definition: BTreeMap<AssetDefinitionK, AssetDefinitionV>,
+    #[serde(with = "entries")]
+    pub account_asset: BTreeMap<AccountAssetK, AccountAssetV>,
+    #[serde(with = "entries")]
+    pub account_permission: BTreeMap<AccountPermissionK, AccountPermissionV>,
+}
+
+impl From<&World> for ArchivedWorld {
+    fn from(world: &World) -> Self {
+        Self {
+            context: world.context,
+            executable: world
+                .executable
+                .iter()
+                .map(|(key, executable)| (key.clone(), executable.hash))
+                .collect(),
+            permission: world.permission.clone(),
+            account: world.account.clone(),
+            asset_definition: world.asset_definition.clone(),
+            account_asset: world.account_asset.clone(),
+            account_permission: world.account_permission.clone(),
+        }
+    }
+}
+
+impl ArchivedWorld {
+    /// Rebuilds the world, looking up each executable by its hash.
+    pub fn restore<E>(
+        self,
+        mut executable: impl FnMut(Hash) -> Result<ExecutableV, E>,
+    ) -> Result<World, E> {
+        Ok(World {
+            context: self.context,
+            executable: self
+                .executable
+                .into_iter()
+                .map(|(key, hash)| Ok((key, executable(hash)?)))
+                .collect::<Result<_, E>>()?,
+            permission: self.permission,
+            account: self.account,
+            asset_definition: self.asset_definition,
+            account_asset: self.account_asset,
+            account_permission: self.account_permission,
+        })
+    }
+}
+
+/// Reasons a write request cannot be applied to the world state.
+#[derive(Debug, Display, Error, PartialEq, Eq)]
+pub enum WriteError {
+    #[display("cannot take {amount} from {key:?} holding {balance}")]
+    InsufficientBalance {
+        key: AccountAssetK,
+        balance: Numeric,
+        amount: Numeric,
+    },
+    #[display("cannot add to {_0:?} beyond the maximum balance")]
+    BalanceOverflow(#[error(not(source))] NodeKey),
+    #[display("cannot express {amount} of {asset:?} kept at scale {scale}")]
+    UnrepresentableAmount {
+        asset: AssetDefinitionK,
+        amount: Numeric,
+        scale: u8,
+    },
+    #[display("cannot take from {_0:?} holding no balance")]
+    NoBalance(#[error(not(source))] NodeKey),
+    #[display("cannot write to {_0:?} which is not registered")]
+    NoAccount(#[error(not(source))] AccountK),
+    #[display("cannot register {_0:?} which is already registered")]
+    AccountExists(#[error(not(source))] AccountK),
+    #[display("cannot unregister {_0:?} which still holds assets")]
+    AccountNotEmpty(#[error(not(source))] AccountK),
+    #[display("cannot write to {_0:?} which is not defined")]
+    NoAssetDefinition(#[error(not(source))] AssetDefinitionK),
+    #[display("cannot register {_0:?} which is already defined")]
+    AssetDefinitionExists(#[error(not(source))] AssetDefinitionK),
+    #[display("cannot unregister {_0:?} which is still in circulation")]
+    AssetInCirculation(#[error(not(source))] AssetDefinitionK),
+    #[display("cannot mint {_0:?} beyond the maximum total supply")]
+    SupplyOverflow(#[error(not(source))] AssetDefinitionK),
+    #[display("cannot burn {amount} of {asset:?} beyond its total supply of {total_supply}")]
+    SupplyUnderflow {
+        asset: AssetDefinitionK,
+        total_supply: Numeric,
+        amount: Numeric,
+    },
+    #[display("cannot write to {_0:?} which is not defined")]
+    NoPermission(#[error(not(source))] PermissionK),
+    #[display("cannot define {_0:?} which is already defined")]
+    PermissionExists(#[error(not(source))] PermissionK),
+    #[display("cannot define {permission:?} allowing another node type than that of {key:?}")]
+    MismatchedAllowance {
+        permission: PermissionK,
+        key: FlexFuzzyNodeKey,
+    },
+    #[display("cannot define {permission:?} setting undefined bits {bits:#010b} on {key:?}")]
+    UndefinedBits {
+        permission: PermissionK,
+        key: FlexFuzzyNodeKey,
+        bits: u8,
+    },
+    #[display("cannot delete {_0:?} which is still granted")]
+    PermissionInUse(#[error(not(source))] PermissionK),
+    #[display("cannot grant {_0:?} which is already granted")]
+    AlreadyGranted(#[error(not(source))] NodeKey),
+    #[display("cannot revoke {_0:?} which is not granted")]
+    NotGranted(#[error(not(source))] NodeKey),
+    #[display("cannot register {_0:?} which is already registered")]
+    ExecutableExists(#[error(not(source))] ExecutableK),
+    #[display("cannot write to {_0:?} which is not registered")]
+    NoExecutable(#[error(not(source))] ExecutableK),
+    #[display("cannot accept {executable:?}: {reason}")]
+    InvalidExecutable {
+        executable: ExecutableK,
+        reason: String,
+    },
+    #[display("cannot unregister the authorizer, only upgrade it")]
+    AuthorizerRequired,
+    #[display("cannot write a value of another node type to {_0:?}")]
+    MismatchedValue(#[error(not(source))] NodeKey),
+}
+
+/// Rejects allowances that [`undefined_bits`] finds on another node type or on undefined events.
+fn check_allowances(permission: &PermissionK, allow: &AllowSet) -> Result<(), WriteError> {
+    for (key, value) in &allow.0 {
+        match undefined_bits(key, value) {
+            None => {
+                return Err(WriteError::MismatchedAllowance {
+                    permission: permission.clone(),
+                    key: key.clone(),
+                });
+            }
+            Some(0) => {}
+            Some(bits) => {
+                return Err(WriteError::UndefinedBits {
+                    permission: permission.clone(),
+                    key: key.clone(),
+                    bits,
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// An asset whose balances do not add up to its recorded total supply.
+#[derive(Debug, Display, Error, PartialEq, Eq)]
+#[display("{asset:?} records a total supply of {total_supply} but accounts hold {held:?}")]
+pub struct SupplyMismatch {
+    pub asset: AssetDefinitionK,
+    pub total_supply: Numeric,
+    /// `None` if the balances overflow when added up.
+    pub held: Option<Numeric>,
+}
+
+/// The world state held in memory.
+///
+/// `executable` must contain the authorizer under [`AUTHORIZER`].
+pub struct World {
+    pub context: Context,
+    pub executable: BTreeMap<ExecutableK, ExecutableV>,
+    pub permission: BTreeMap<PermissionK, PermissionV>,
+    pub account: BTreeMap<AccountK, AccountV>,
+    pub asset_definition: BTreeMap<AssetDefinitionK, AssetDefinitionV>,
+    pub account_asset: BTreeMap<AccountAssetK, AccountAssetV>,
+    pub account_permission: BTreeMap<AccountPermissionK, AccountPermissionV>,
+}
+
+impl World {
+    /// Replaces the authorizer after checking that it implements the `authorizer` world.
+    ///
+    /// Bypasses authorization, so it is only meant for setting up a world. Running worlds replace
+    /// it with [`UpgradeAuthorizer`](crate::instruction::BuiltinInstruction::UpgradeAuthorizer).
+    pub fn set_authorizer(
+        &mut self,
+        authorizer: ExecutableV,
+    ) -> Result<(), crate::instruction::InstructionError> {
+        crate::instruction::validate_authorizer(&authorizer.component)?;
+        self.executable
+            .insert(SingleKey(AUTHORIZER.into()), authorizer);
+
+        Ok(())
+    }
+
+    /// Checks that the balances of every asset add up to its recorded total supply.
+    pub fn audit_supply(&self) -> Result<(), SupplyMismatch> {
+        let mut held: BTreeMap<&KeyElem, Option<Numeric>> = self
+            .asset_definition
+            .iter()
+            .map(|(asset, definition)| (&asset.0, Some(Numeric::new(0, definition.scale))))
+            .collect();
+        for (CompositeKey(_, asset), AccountAssetV { balance }) in &self.account_asset {
+            let held = held.entry(asset).or_insert(Some(Numeric::default()));
+            *held = held.and_then(|held| held.checked_add(*balance));
+        }
+        for (asset, held) in held {
+            let asset = SingleKey(asset.clone());
+            // Balances of an undefined asset count against a supply of zero
+            let total_supply = self
+                .asset_definition
+                .get(&asset)
+                .map_or(Numeric::default(), |definition| definition.total_supply);
+            let balanced = held
+                .and_then(|held| held.checked_sub(total_supply))
+                .is_some_and(|surplus| surplus.is_zero());
+            if !balanced {
+                return Err(SupplyMismatch {
+                    asset,
+                    total_supply,
+                    held,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl WorldState for World {
+    fn context(&self) -> Context {
+        self.context
+    }
+
+    fn executable(&self, executable: &ExecutableK) -> Option<&ExecutableV> {
+        self.executable.get(executable)
+    }
+
     fn read(&self, request: &ReadSet) -> ViewSet {
-        let keys: Vec<&AccountAssetK> = self
-            .account_asset
-            .keys()
-            .filter(|key| {
-                println!("Checking key: {key:?}");
-                request
-                    .0
-                    .keys()
-                    .inspect(|fuzzy_key| {
-                        println!("Against fuzzy key: {fuzzy_key:?}");
-                    })
-                    .any(|FuzzyNodeKey::AccountAsset(capture)| {
-                        let captured = capture.captures(key);
-                        println!("Captured: {captured}");
-                        captured
-                    })
+        let captured = |key: &NodeKey| {
+            request.0.keys().any(|capture| {
+                let captured = capture.captures(key);
+                println!("Checking key {key:?} against {capture:?}: {captured}");
+                captured
             })
-            .collect();
-        println!("Keys to read: {:#?}", &keys);
-        let map = self
-            .account_asset
+        };
+        let account = self
+            .account
             .iter()
-            .filter(|(key, _)| keys.contains(key))
-            .map(|(k, v)| {
-                (
-                    NodeKey::AccountAsset(k.clone()),
-                    NodeValue::AccountAsset(v.clone()),
-                )
-            })
+            .map(|(k, v)| (NodeKey::Account(k.clone()), NodeValue::Account(v.clone())));
+        let asset_definition = self.asset_definition.iter().map(|(k, v)| {
+            (
+                NodeKey::AssetDefinition(k.clone()),
+                NodeValue::AssetDefinition(v.clone()),
+            )
+        });
+        let account_asset = self.account_asset.iter().map(|(k, v)| {
+            (
+                NodeKey::AccountAsset(k.clone()),
+                NodeValue::AccountAsset(v.clone()),
+            )
+        });
+        let permission = self.permission.iter().map(|(k, v)| {
+            (
+                NodeKey::Permission(k.clone()),
+                NodeValue::Permission(v.clone()),
+            )
+        });
+        let account_permission = self.account_permission.iter().map(|(k, v)| {
+            (
+                NodeKey::AccountPermission(k.clone()),
+                NodeValue::AccountPermission(v.clone()),
+            )
+        });
+        let executable = self.executable.iter().map(|(k, v)| {
+            (
+                NodeKey::Executable(k.clone()),
+                NodeValue::Executable(v.clone()),
+            )
+        });
+        let map = account
+            .chain(asset_definition)
+            .chain(account_asset)
+            .chain(permission)
+            .chain(account_permission)
+            .chain(executable)
+            .filter(|(key, _)| captured(key))
             .collect();
         println!("Read map: {:#?}", &map);
 
         Tree(map)
     }
 
-    fn write(&mut self, request: &WriteSet, authority: AccountK) {
-        request.0.iter().for_each(
-            |(FlexNodeKey::AccountAsset(k), NodeValue::AccountAsset(v))| match v {
-                AccountAssetW::Receive(amount) => {
-                    self.account_asset
-                        .entry(k.clone().resolve(authority.0.clone()))
-                        .and_modify(|existing| {
-                            let AccountAssetV { balance } = existing;
-                            println!("Adding amount: {k:?}");
-                            println!("Current balance: {}", &balance);
-                            *balance = balance.saturating_add(*amount);
-                            println!("New balance: {balance}");
-                        })
-                        .or_insert_with(|| AccountAssetV { balance: *amount });
-                }
-                AccountAssetW::Send(amount) => {
-                    self.account_asset
-                        .entry(k.clone().resolve(authority.0.clone()))
-                        .and_modify(|existing| {
-                            let AccountAssetV { balance } = existing;
-                            println!("Subtracting amount: {k:?}");
-                            println!("Current balance: {}", &balance);
-                            *balance = balance.checked_sub(*amount).unwrap_or_else(|| {
-                                panic!("Cannot send more than the balance");
-                            });
-                            println!("New balance: {balance}");
-                        })
-                        .or_insert_with(|| {
-                            panic!("Cannot send from no balance");
-                        });
+    fn commit(&mut self, diff: StateDiff) -> std::io::Result<()> {
+        for (key, value) in diff.0 {
+            match (key, value) {
+                (NodeKey::Account(k), Some(NodeValue::Account(v))) => {
+                    self.account.insert(k, v);
                 }
-            },
-        )
+                (NodeKey::Account(k), None) => {
+                    self.account.remove(&k);
+                }
+                (NodeKey::AssetDefinition(k), Some(NodeValue::AssetDefinition(v))) => {
+                    self.asset_definition.insert(k, v);
+                }
+                (NodeKey::AssetDefinition(k), None) => {
+                    self.asset_definition.remove(&k);
+                }
+                (NodeKey::AccountAsset(k), Some(NodeValue::AccountAsset(v))) => {
+                    self.account_asset.insert(k, v);
+                }
+                (NodeKey::AccountAsset(k), None) => {
+                    self.account_asset.remove(&k);
+                }
+                (NodeKey::Permission(k), Some(NodeValue::Permission(v))) => {
+                    self.permission.insert(k, v);
+                }
+                (NodeKey::Permission(k), None) => {
+                    self.permission.remove(&k);
+                }
+                (NodeKey::AccountPermission(k), Some(NodeValue::AccountPermission(v))) => {
+                    self.account_permission.insert(k, v);
+                }
+                (NodeKey::AccountPermission(k), None) => {
+                    self.account_permission.remove(&k);
+                }
+                (NodeKey::Executable(k), Some(NodeValue::Executable(v))) => {
+                    self.executable.insert(k, v);
+                }
+                (NodeKey::Executable(k), None) => {
+                    self.executable.remove(&k);
+                }
+                (key, value) => unreachable!("staged {value:?} on {key:?}"),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Copy-on-write view over a world state.
+///
+/// Commits are staged in the overlay and visible to its reads, while the base stays untouched.
+pub struct Overlay<'w, W> {
+    base: &'w W,
+    staged: StateDiff,
+}
+
+impl<'w, W: WorldState> Overlay<'w, W> {
+    pub fn new(base: &'w W) -> Self {
+        Self {
+            base,
+            staged: StateDiff::default(),
+        }
+    }
+
+    /// Takes out everything staged so far, to be committed to the base.
+    pub fn into_diff(self) -> StateDiff {
+        self.staged
+    }
+}
+
+impl<W: WorldState> WorldState for Overlay<'_, W> {
+    fn context(&self) -> Context {
+        self.base.context()
+    }
+
+    fn executable(&self, executable: &ExecutableK) -> Option<&ExecutableV> {
+        match self.staged.0.get(&NodeKey::Executable(executable.clone())) {
+            Some(Some(NodeValue::Executable(staged))) => Some(staged),
+            Some(_) => None,
+            None => self.base.executable(executable),
+        }
+    }
+
+    fn read(&self, request: &ReadSet) -> ViewSet {
+        let mut view = self.base.read(request);
+        let staged = self
+            .staged
+            .0
+            .iter()
+            .filter(|(key, _)| request.0.keys().any(|capture| capture.captures(key)));
+        for (key, value) in staged {
+            match value {
+                Some(value) => view.0.insert(key.clone(), value.clone()),
+                None => view.0.remove(key),
+            };
+        }
+
+        view
+    }
+
+    fn commit(&mut self, diff: StateDiff) -> std::io::Result<()> {
+        self.staged.0.extend(diff.0);
+
+        Ok(())
     }
 }
@@ -0,0 +1,404 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::commitment::{Proof, StateTree};
+use crate::prelude::*;
+use crate::state::{ArchivedDiff, ArchivedWorld, StateDiff, World, WorldState};
+
+/// Where a [`Stored`] world keeps its commits so that they outlive the process.
+pub trait Storage {
+    /// Makes a validated diff durable, so that it survives a crash once this returns.
+    fn append(&mut self, diff: &StateDiff) -> io::Result<()>;
+
+    /// Offers the state right after a commit, to be snapshotted whenever the storage sees fit.
+    fn snapshot(&mut self, world: &World) -> io::Result<()>;
+}
+
+/// Keeps nothing beyond the world in memory.
+#[derive(Debug, Default)]
+pub struct Memory;
+
+impl Storage for Memory {
+    fn append(&mut self, _diff: &StateDiff) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn snapshot(&mut self, _world: &World) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A world whose commits go through its storage before taking effect.
+///
+/// Derefs to the world in memory for inspection, while changes go through [`WorldState::commit`].
+/// Keeps the [`StateTree`] of the world up to date with every commit.
+pub struct Stored<S> {
+    world: World,
+    storage: S,
+    tree: StateTree,
+}
+
+impl<S: Storage> Stored<S> {
+    pub fn new(world: World, storage: S) -> Self {
+        let tree = StateTree::new(&world);
+        Self {
+            world,
+            storage,
+            tree,
+        }
+    }
+
+    /// State tree as of the last commit.
+    pub fn tree(&self) -> &StateTree {
+        &self.tree
+    }
+
+    /// Root of the state tree as of the last commit.
+    pub fn root(&self) -> Hash {
+        self.tree.root()
+    }
+
+    /// Reads like [`WorldState::read`], along with a proof against [`Stored::root`] for every
+    /// entry of the view.
+    pub fn read_with_proofs(&self, request: &ReadSet) -> (ViewSet, BTreeMap<NodeKey, Proof>) {
+        let view = self.world.read(request);
+        let proofs = view
+            .0
+            .keys()
+            .map(|key| (key.clone(), self.tree.prove(key)))
+            .collect();
+
+        (view, proofs)
+    }
+
+    pub fn into_inner(self) -> (World, S) {
+        (self.world, self.storage)
+    }
+}
+
+impl<S> Deref for Stored<S> {
+    type Target = World;
+
+    fn deref(&self) -> &World {
+        &self.world
+    }
+}
+
+impl<S: Storage> WorldState for Stored<S> {
+    fn context(&self) -> Context {
+        self.world.context()
+    }
+
+    fn executable(&self, executable: &ExecutableK) -> Option<&ExecutableV> {
+        self.world.executable(executable)
+    }
+
+    fn read(&self, request: &ReadSet) -> ViewSet {
+        self.world.read(request)
+    }
+
+    /// Leaves the world as it was if the diff cannot be made durable.
+    fn commit(&mut self, diff: StateDiff) -> io::Result<()> {
+        self.storage.append(&diff)?;
+        self.tree.apply(&diff);
+        self.world.commit(diff)?;
+        if let Err(error) = self.storage.snapshot(&self.world) {
+            // Every commit is still in the log, so only reopening gets slower
+            log::warn!("snapshot failed: {error}");
+        }
+
+        Ok(())
+    }
+
+    fn state_root(&self) -> Option<Hash> {
+        Some(self.root())
+    }
+
+    fn prove(&self, key: &NodeKey) -> Option<Proof> {
+        Some(self.tree.prove(key))
+    }
+}
+
+/// Commits after which [`Disk`] takes a snapshot by default.
+pub const SNAPSHOT_INTERVAL: u64 = 100;
+
+const SNAPSHOT: &str = "snapshot.json";
+const LOG: &str = "log.jsonl";
+const EXECUTABLE: &str = "executable";
+
+/// Keeps the state in a directory as a snapshot, followed by a log of the diffs committed since.
+///
+/// ```text
+/// snapshot.json            the world as of commit `seq`, executables by hash
+/// log.jsonl                one `{seq, diff}` per line, synced before the commit takes effect
+/// executable/<hash>.wasm   component bytes, written before any entry refers to them
+/// ```
+///
+/// A crash can at worst leave a torn last line in the log, which reopening discards along with
+/// the commit it never completed.
+pub struct Disk {
+    dir: PathBuf,
+    log: File,
+    /// Last commit appended to the log
+    seq: u64,
+    /// Last commit included in the snapshot
+    snapshot_seq: u64,
+    interval: u64,
+    /// Set once the log could not be restored after a failed append
+    poisoned: bool,
+    /// Bytes of the next line to write before failing, to stand in for a full disk
+    #[cfg(test)]
+    tear: Option<usize>,
+}
+
+/// The world as of commit `seq`.
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    seq: u64,
+    world: ArchivedWorld,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    seq: u64,
+    diff: ArchivedDiff,
+}
+
+impl Disk {
+    /// Starts storing the world in `dir`, which must not hold a store already.
+    pub fn create(dir: impl AsRef<Path>, world: &World) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(dir.join(EXECUTABLE))?;
+        if dir.join(SNAPSHOT).exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already holds a world", dir.display()),
+            ));
+        }
+        let mut disk = Self {
+            log: File::create(dir.join(LOG))?,
+            dir,
+            seq: 0,
+            snapshot_seq: 0,
+            interval: SNAPSHOT_INTERVAL,
+            poisoned: false,
+            #[cfg(test)]
+            tear: None,
+        };
+        disk.write_snapshot(world)?;
+
+        Ok(disk)
+    }
+
+    /// Reopens the store in `dir`, recovering the world as of its last durable commit.
+    ///
+    /// Executables are compiled on `engine`.
+    pub fn open(dir: impl AsRef<Path>, engine: &wasmtime::Engine) -> io::Result<(Self, World)> {
+        let dir = dir.as_ref().to_path_buf();
+        let snapshot: Snapshot = serde_json::from_slice(&fs::read(dir.join(SNAPSHOT))?)?;
+        let mut compiled = BTreeMap::<Hash, ExecutableV>::new();
+        let mut executable = |hash: Hash| -> io::Result<ExecutableV> {
+            if let Some(executable) = compiled.get(&hash) {
+                return Ok(executable.clone());
+            }
+            let bytes = fs::read(blob(&dir, &hash))?;
+            let executable =
+                ExecutableV::new(engine, &bytes).map_err(|error| invalid(format!("{error:#}")))?;
+            if executable.hash != hash {
+                return Err(invalid(format!("executable {} is corrupt", hex(&hash))));
+            }
+            compiled.insert(hash, executable.clone());
+            Ok(executable)
+        };
+
+        let mut world = snapshot.world.restore(&mut executable)?;
+
+        let log = fs::read(dir.join(LOG))?;
+        let mut seq = snapshot.seq;
+        let mut durable = 0;
+        let mut lines = log.split_inclusive(|&byte| byte == b'\n').peekable();
+        while let Some(line) = lines.next() {
+            let entry = match serde_json::from_slice::<Entry>(line) {
+                Ok(entry) if line.ends_with(b"\n") => entry,
+                // Torn by a crash while appending, so the commit never took effect
+                _ if lines.peek().is_none() => break,
+                _ => return Err(invalid(format!("log is corrupt at byte {durable}"))),
+            };
+            durable += line.len();
+            // Already in the snapshot if a crash came between snapshotting and resetting the log
+            if entry.seq <= snapshot.seq {
+                continue;
+            }
+            if entry.seq != seq + 1 {
+                return Err(invalid(format!("log skips from {seq} to {}", entry.seq)));
+            }
+            seq = entry.seq;
+            let diff = entry
+                .diff
+                .0
+                .into_iter()
+                .map(|(key, value)| {
+                    let value = value.map(|value| restore(value, &mut executable));
+                    Ok((key, value.transpose()?))
+                })
+                .collect::<io::Result<_>>()?;
+            world.commit(StateDiff(diff))?;
+        }
+
+        let log = OpenOptions::new().append(true).open(dir.join(LOG))?;
+        log.set_len(durable as u64)?;
+        log.sync_all()?;
+        let disk = Self {
+            dir,
+            log,
+            seq,
+            snapshot_seq: snapshot.seq,
+            interval: SNAPSHOT_INTERVAL,
+            poisoned: false,
+            #[cfg(test)]
+            tear: None,
+        };
+
+        Ok((disk, world))
+    }
+
+    /// Fails the next append after writing only `bytes` of its line.
+    #[cfg(test)]
+    pub(crate) fn tear_next_append(&mut self, bytes: usize) {
+        self.tear = Some(bytes);
+    }
+
+    /// Takes a snapshot after every `commits` commits instead of [`SNAPSHOT_INTERVAL`].
+    pub fn snapshot_every(mut self, commits: u64) -> Self {
+        self.interval = commits.max(1);
+        self
+    }
+
+    fn write_snapshot(&mut self, world: &World) -> io::Result<()> {
+        for executable in world.executable.values() {
+            self.write_blob(executable)?;
+        }
+        let snapshot = Snapshot {
+            seq: self.seq,
+            world: world.into(),
+        };
+        write_atomic(&self.dir, SNAPSHOT, &serde_json::to_vec(&snapshot)?)?;
+        self.snapshot_seq = self.seq;
+
+        // The snapshot covers the whole log by now. The new log is opened before it replaces the
+        // old one, so that a failure leaves the old log both in place and in use
+        let path = self.dir.join(LOG);
+        let temporary = path.with_extension("tmp");
+        File::create(&temporary)?.sync_all()?;
+        let log = OpenOptions::new().append(true).open(&temporary)?;
+        fs::rename(&temporary, &path)?;
+        self.log = log;
+        File::open(&self.dir)?.sync_all()
+    }
+
+    fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        #[cfg(test)]
+        if let Some(bytes) = self.tear.take() {
+            self.log.write_all(&line[..bytes.min(line.len())])?;
+            return Err(io::Error::other("disk full"));
+        }
+        self.log.write_all(line)?;
+        self.log.sync_data()
+    }
+
+    fn write_blob(&self, executable: &ExecutableV) -> io::Result<()> {
+        let path = blob(&self.dir, &executable.hash);
+        if path.exists() {
+            return Ok(());
+        }
+        let name = path
+            .strip_prefix(&self.dir)
+            .expect("blob should be in the store");
+        write_atomic(&self.dir, name, &executable.bytes)
+    }
+}
+
+impl Storage for Disk {
+    fn append(&mut self, diff: &StateDiff) -> io::Result<()> {
+        if self.poisoned {
+            return Err(io::Error::other(
+                "log is torn by a failed append, reopen the store",
+            ));
+        }
+        for value in diff.0.values() {
+            if let Some(NodeValue::Executable(executable)) = value {
+                self.write_blob(executable)?;
+            }
+        }
+        let entry = Entry {
+            seq: self.seq + 1,
+            diff: diff.clone().into(),
+        };
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        let durable = self.log.metadata()?.len();
+        if let Err(error) = self.write_line(&line) {
+            // Lines appended after a partial one would make the log unreadable
+            self.poisoned = self
+                .log
+                .set_len(durable)
+                .and_then(|()| self.log.sync_data())
+                .is_err();
+            return Err(error);
+        }
+        self.seq = entry.seq;
+
+        Ok(())
+    }
+
+    fn snapshot(&mut self, world: &World) -> io::Result<()> {
+        if self.seq - self.snapshot_seq < self.interval {
+            return Ok(());
+        }
+        self.write_snapshot(world)
+    }
+}
+
+/// Resolves an archived value, compiling executables from their stored bytes.
+fn restore(
+    value: NodeValue<Archive>,
+    executable: impl FnOnce(Hash) -> io::Result<ExecutableV>,
+) -> io::Result<NodeValue<View>> {
+    Ok(match value {
+        NodeValue::Account(value) => NodeValue::Account(value),
+        NodeValue::AssetDefinition(value) => NodeValue::AssetDefinition(value),
+        NodeValue::AccountAsset(value) => NodeValue::AccountAsset(value),
+        NodeValue::Permission(value) => NodeValue::Permission(value),
+        NodeValue::AccountPermission(value) => NodeValue::AccountPermission(value),
+        NodeValue::Executable(hash) => NodeValue::Executable(executable(hash)?),
+    })
+}
+
+/// Replaces the file at `dir/name` so that a crash leaves either the old or the new contents.
+fn write_atomic(dir: &Path, name: impl AsRef<Path>, contents: &[u8]) -> io::Result<()> {
+    let path = dir.join(name);
+    let temporary = path.with_extension("tmp");
+    let mut file = File::create(&temporary)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&temporary, &path)?;
+    // Makes the rename itself durable
+    File::open(path.parent().unwrap_or(dir))?.sync_all()
+}
+
+fn blob(dir: &Path, hash: &Hash) -> PathBuf {
+    dir.join(EXECUTABLE).join(format!("{}.wasm", hex(hash)))
+}
+
+fn hex(hash: &Hash) -> String {
+    hash.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,54 @@
+use crate::instruction::{InstructionEnum, InstructionError, Record};
+use crate::prelude as host;
+use crate::state::{Overlay, WorldState};
+
+use derive_more::{Display, Error};
+use serde::{Deserialize, Serialize};
+
+/// Instructions submitted together by one authority, applied all or nothing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transaction {
+    pub authority: host::AccountK,
+    pub instructions: Vec<InstructionEnum>,
+}
+
+/// The first instruction that failed, which discards the whole transaction.
+///
+/// A commit that fails after every instruction succeeded is reported at the index past the last.
+#[derive(Debug, Display, Error)]
+#[display("instruction {index} failed: {source}")]
+pub struct TransactionError {
+    pub index: usize,
+    pub source: Box<InstructionError>,
+}
+
+impl Transaction {
+    /// Runs every instruction against an overlay of the world, so that later instructions see
+    /// earlier writes, and commits the overlay only if all of them succeed.
+    ///
+    /// Returns the record of every instruction in order.
+    pub fn execute(self, world: &mut impl WorldState) -> Result<Vec<Record>, TransactionError> {
+        let Transaction {
+            authority,
+            instructions,
+        } = self;
+        let mut overlay = Overlay::new(&*world);
+        let mut records = Vec::with_capacity(instructions.len());
+        for (index, instruction) in instructions.into_iter().enumerate() {
+            let record = instruction
+                .execute(authority.clone(), &mut overlay)
+                .map_err(|source| TransactionError {
+                    index,
+                    source: Box::new(source),
+                })?;
+            records.push(record);
+        }
+        let diff = overlay.into_diff();
+        world.commit(diff).map_err(|source| TransactionError {
+            index: records.len(),
+            source: Box::new(InstructionError::Storage(source)),
+        })?;
+
+        Ok(records)
+    }
+}
@@ -1,25 +1,162 @@
 pub mod general {
+    use serde::{Deserialize, Serialize};
     use std::collections::BTreeMap;
 
     pub trait Mode {
-        // PoC only supports AccountAsset variant
+        type Account: std::fmt::Debug + Clone + PartialEq + Eq;
+        type AssetDefinition: std::fmt::Debug + Clone + PartialEq + Eq;
         type AccountAsset: std::fmt::Debug + Clone + PartialEq + Eq;
+        type Permission: std::fmt::Debug + Clone + PartialEq + Eq;
+        type AccountPermission: std::fmt::Debug + Clone + PartialEq + Eq;
+        type Executable: std::fmt::Debug + Clone + PartialEq + Eq;
     }
 
-    #[derive(Debug, Clone, PartialEq, Eq)]
-    pub struct Tree<T: Mode>(pub BTreeMap<NodeKey, NodeValue<T>>);
+    /// (De)serializes a map as a list of `[key, value]` pairs, since JSON keys are only strings.
+    pub mod entries {
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+        use std::collections::BTreeMap;
 
-    #[derive(Debug, Clone, PartialEq, Eq)]
-    pub struct FlexTree<T: Mode>(pub BTreeMap<FlexNodeKey, NodeValue<T>>);
+        pub fn serialize<K: Serialize, V: Serialize, S: Serializer>(
+            map: &BTreeMap<K, V>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(map)
+        }
+
+        pub fn deserialize<'de, K, V, D>(deserializer: D) -> Result<BTreeMap<K, V>, D::Error>
+        where
+            K: Deserialize<'de> + Ord,
+            V: Deserialize<'de>,
+            D: Deserializer<'de>,
+        {
+            Vec::<(K, V)>::deserialize(deserializer).map(|entries| entries.into_iter().collect())
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(bound(
+        serialize = "NodeValue<T>: Serialize",
+        deserialize = "NodeValue<T>: Deserialize<'de>"
+    ))]
+    pub struct Tree<T: Mode>(#[serde(with = "entries")] pub BTreeMap<NodeKey, NodeValue<T>>);
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(bound(
+        serialize = "NodeValue<T>: Serialize",
+        deserialize = "NodeValue<T>: Deserialize<'de>"
+    ))]
+    pub struct FlexTree<T: Mode>(
+        #[serde(with = "entries")] pub BTreeMap<FlexNodeKey, NodeValue<T>>,
+    );
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(bound(
+        serialize = "NodeValue<T>: Serialize",
+        deserialize = "NodeValue<T>: Deserialize<'de>"
+    ))]
+    pub struct FuzzyTree<T: Mode>(
+        #[serde(with = "entries")] pub BTreeMap<FuzzyNodeKey, NodeValue<T>>,
+    );
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(bound(
+        serialize = "NodeValue<T>: Serialize",
+        deserialize = "NodeValue<T>: Deserialize<'de>"
+    ))]
+    pub struct FlexFuzzyTree<T: Mode>(
+        #[serde(with = "entries")] pub BTreeMap<FlexFuzzyNodeKey, NodeValue<T>>,
+    );
+
+    /// Block the world state is at when an instruction runs.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct Context {
+        pub height: u64,
+        pub timestamp: u64,
+    }
+
+    /// Fixed-point decimal number worth `mantissa * 10^-scale`.
+    ///
+    /// Arithmetic is checked and aligns operands to the larger scale; equality is by
+    /// representation, so `1.0` and `1.00` differ.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct Numeric {
+        pub mantissa: u128,
+        pub scale: u8,
+    }
+
+    impl Numeric {
+        pub const fn new(mantissa: u128, scale: u8) -> Self {
+            Self { mantissa, scale }
+        }
+
+        pub const fn is_zero(&self) -> bool {
+            self.mantissa == 0
+        }
+
+        /// Expresses the same value at another scale, if it fits without losing precision.
+        pub fn rescale(self, scale: u8) -> Option<Self> {
+            let mantissa = if self.scale <= scale {
+                let factor = 10u128.checked_pow((scale - self.scale).into())?;
+                self.mantissa.checked_mul(factor)?
+            } else {
+                match 10u128.checked_pow((self.scale - scale).into()) {
+                    Some(divisor) if self.mantissa.is_multiple_of(divisor) => {
+                        self.mantissa / divisor
+                    }
+                    // Nothing but zero is a multiple of a divisor beyond the mantissa range
+                    None if self.is_zero() => 0,
+                    _ => return None,
+                }
+            };
+
+            Some(Self { mantissa, scale })
+        }
 
-    #[derive(Debug, Clone, PartialEq, Eq)]
-    pub struct FuzzyTree<T: Mode>(pub BTreeMap<FuzzyNodeKey, NodeValue<T>>);
+        pub fn checked_add(self, rhs: Self) -> Option<Self> {
+            let scale = self.scale.max(rhs.scale);
+            let mantissa = self
+                .rescale(scale)?
+                .mantissa
+                .checked_add(rhs.rescale(scale)?.mantissa)?;
+
+            Some(Self { mantissa, scale })
+        }
 
-    #[derive(Debug, Clone, PartialEq, Eq)]
-    pub struct FlexFuzzyTree<T: Mode>(pub BTreeMap<FlexFuzzyNodeKey, NodeValue<T>>);
+        /// Returns `None` on overflow or if `rhs` is greater than `self`.
+        pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+            let scale = self.scale.max(rhs.scale);
+            let mantissa = self
+                .rescale(scale)?
+                .mantissa
+                .checked_sub(rhs.rescale(scale)?.mantissa)?;
+
+            Some(Self { mantissa, scale })
+        }
+    }
+
+    impl From<u128> for Numeric {
+        fn from(mantissa: u128) -> Self {
+            Self::new(mantissa, 0)
+        }
+    }
+
+    impl std::fmt::Display for Numeric {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            let scale = usize::from(self.scale);
+            if scale == 0 {
+                return write!(f, "{}", self.mantissa);
+            }
+            let digits = format!("{:0>width$}", self.mantissa, width = scale + 1);
+            let (int, frac) = digits.split_at(digits.len() - scale);
+            write!(f, "{int}.{frac}")
+        }
+    }
+
+    /// SHA-256 digest.
+    pub type Hash = [u8; 32];
 
     pub type KeyElem = String;
-    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
     pub enum FlexKeyElem {
         /// Generic pointer to the current account; resolved to an absolute KeyElem
         This,
@@ -27,58 +164,92 @@ pub mod general {
         That(KeyElem),
     }
 
-    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
     pub struct SingleKey(pub KeyElem);
-    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
     pub struct FlexSingleKey(pub FlexKeyElem);
-    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
     pub struct CompositeKey(pub KeyElem, pub KeyElem);
-    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
     pub struct FlexCompositeKey(pub FlexKeyElem, pub KeyElem);
 
     pub type AccountK = SingleKey;
+    pub type AssetDefinitionK = SingleKey;
     pub type ExecutableK = SingleKey;
     pub type PermissionK = SingleKey;
     pub type AccountAssetK = CompositeKey;
     pub type AccountPermissionK = CompositeKey;
+    pub type FlexAccountK = FlexSingleKey;
     pub type FlexAccountAssetK = FlexCompositeKey;
+    pub type FlexAccountPermissionK = FlexCompositeKey;
 
-    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
     pub struct FuzzySingleKey(pub Option<KeyElem>);
-    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
     pub struct FlexFuzzySingleKey(pub Option<FlexKeyElem>);
-    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
     pub struct FuzzyCompositeKey(pub Option<KeyElem>, pub Option<KeyElem>);
-    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
     pub struct FlexFuzzyCompositeKey(pub Option<FlexKeyElem>, pub Option<KeyElem>);
 
     pub type FuzzyAccountK = FuzzySingleKey;
+    pub type FuzzyAssetDefinitionK = FuzzySingleKey;
     pub type FuzzyAccountAssetK = FuzzyCompositeKey;
+    pub type FuzzyPermissionK = FuzzySingleKey;
+    pub type FuzzyAccountPermissionK = FuzzyCompositeKey;
+    pub type FuzzyExecutableK = FuzzySingleKey;
+    pub type FlexFuzzyAccountK = FlexFuzzySingleKey;
     pub type FlexFuzzyAccountAssetK = FlexFuzzyCompositeKey;
+    pub type FlexFuzzyAccountPermissionK = FlexFuzzyCompositeKey;
 
-    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
     pub enum NodeKey {
+        Account(AccountK),
+        AssetDefinition(AssetDefinitionK),
         AccountAsset(AccountAssetK),
+        Permission(PermissionK),
+        AccountPermission(AccountPermissionK),
+        Executable(ExecutableK),
     }
 
-    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
     pub enum FlexNodeKey {
+        Account(FlexAccountK),
+        AssetDefinition(AssetDefinitionK),
         AccountAsset(FlexAccountAssetK),
+        Permission(PermissionK),
+        AccountPermission(FlexAccountPermissionK),
+        Executable(ExecutableK),
     }
 
-    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
     pub enum FuzzyNodeKey {
+        Account(FuzzyAccountK),
+        AssetDefinition(FuzzyAssetDefinitionK),
         AccountAsset(FuzzyAccountAssetK),
+        Permission(FuzzyPermissionK),
+        AccountPermission(FuzzyAccountPermissionK),
+        Executable(FuzzyExecutableK),
     }
 
-    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
     pub enum FlexFuzzyNodeKey {
+        Account(FlexFuzzyAccountK),
+        AssetDefinition(FuzzyAssetDefinitionK),
         AccountAsset(FlexFuzzyAccountAssetK),
+        Permission(FuzzyPermissionK),
+        AccountPermission(FlexFuzzyAccountPermissionK),
+        Executable(FuzzyExecutableK),
     }
 
-    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
     pub enum NodeValue<T: Mode> {
+        Account(T::Account),
+        AssetDefinition(T::AssetDefinition),
         AccountAsset(T::AccountAsset),
+        Permission(T::Permission),
+        AccountPermission(T::AccountPermission),
+        Executable(T::Executable),
     }
 
     pub trait Capture {
@@ -103,6 +274,33 @@ pub mod general {
         }
     }
 
+    impl Capture for FuzzyNodeKey {
+        type Captured = NodeKey;
+        fn captures(&self, candidate: &Self::Captured) -> bool {
+            match (self, candidate) {
+                (FuzzyNodeKey::Account(cap), NodeKey::Account(candidate)) => {
+                    cap.captures(candidate)
+                }
+                (FuzzyNodeKey::AssetDefinition(cap), NodeKey::AssetDefinition(candidate)) => {
+                    cap.captures(candidate)
+                }
+                (FuzzyNodeKey::AccountAsset(cap), NodeKey::AccountAsset(candidate)) => {
+                    cap.captures(candidate)
+                }
+                (FuzzyNodeKey::Permission(cap), NodeKey::Permission(candidate)) => {
+                    cap.captures(candidate)
+                }
+                (FuzzyNodeKey::AccountPermission(cap), NodeKey::AccountPermission(candidate)) => {
+                    cap.captures(candidate)
+                }
+                (FuzzyNodeKey::Executable(cap), NodeKey::Executable(candidate)) => {
+                    cap.captures(candidate)
+                }
+                _ => false,
+            }
+        }
+    }
+
     /// Resolves FlexKeyElem::This to absolute KeyElem.
     pub trait Resolve {
         type Resolved;
@@ -164,7 +362,14 @@ pub mod general {
         type Resolved = NodeKey;
         fn resolve(self, this: KeyElem) -> Self::Resolved {
             match self {
+                FlexNodeKey::Account(key) => NodeKey::Account(key.resolve(this)),
+                FlexNodeKey::AssetDefinition(key) => NodeKey::AssetDefinition(key),
                 FlexNodeKey::AccountAsset(key) => NodeKey::AccountAsset(key.resolve(this)),
+                FlexNodeKey::Permission(key) => NodeKey::Permission(key),
+                FlexNodeKey::AccountPermission(key) => {
+                    NodeKey::AccountPermission(key.resolve(this))
+                }
+                FlexNodeKey::Executable(key) => NodeKey::Executable(key),
             }
         }
     }
@@ -173,9 +378,16 @@ pub mod general {
         type Resolved = FuzzyNodeKey;
         fn resolve(self, this: KeyElem) -> Self::Resolved {
             match self {
+                FlexFuzzyNodeKey::Account(key) => FuzzyNodeKey::Account(key.resolve(this)),
+                FlexFuzzyNodeKey::AssetDefinition(key) => FuzzyNodeKey::AssetDefinition(key),
                 FlexFuzzyNodeKey::AccountAsset(key) => {
                     FuzzyNodeKey::AccountAsset(key.resolve(this))
                 }
+                FlexFuzzyNodeKey::Permission(key) => FuzzyNodeKey::Permission(key),
+                FlexFuzzyNodeKey::AccountPermission(key) => {
+                    FuzzyNodeKey::AccountPermission(key.resolve(this))
+                }
+                FlexFuzzyNodeKey::Executable(key) => FuzzyNodeKey::Executable(key),
             }
         }
     }
@@ -207,90 +419,281 @@ pub mod general {
 
 pub mod read {
     use super::general::*;
+    use serde::{Deserialize, Serialize};
 
-    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
     pub struct Read;
 
     impl Mode for Read {
+        type Account = AccountR;
+        type AssetDefinition = AssetDefinitionR;
         type AccountAsset = AccountAssetR;
+        type Permission = PermissionR;
+        type AccountPermission = AccountPermissionR;
+        type Executable = ExecutableR;
     }
 
     pub type ReadSet = FuzzyTree<Read>;
 
-    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct AccountR;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct AssetDefinitionR;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
     pub struct AccountAssetR;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct PermissionR;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct AccountPermissionR;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct ExecutableR;
 }
 
 pub mod view {
     use super::general::*;
     use derive_more::Debug;
+    use serde::{Deserialize, Serialize};
 
-    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
     pub struct View;
 
     impl Mode for View {
+        type Account = AccountV;
+        type AssetDefinition = AssetDefinitionV;
         type AccountAsset = AccountAssetV;
+        type Permission = PermissionV;
+        type AccountPermission = AccountPermissionV;
+        type Executable = ExecutableV;
     }
 
     pub type ViewSet = Tree<View>;
 
-    #[derive(Debug, Clone, PartialEq, Eq)]
+    /// An account carries nothing yet besides its existence.
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct AccountV;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct AssetDefinitionV {
+        /// The account that registered the asset
+        pub owner: AccountK,
+        /// Decimal places every amount of the asset is kept at
+        pub scale: u8,
+        /// Sum of all balances of the asset, kept up to date by mints and burns
+        pub total_supply: Numeric,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
     pub struct AccountAssetV {
-        pub balance: u32,
+        pub balance: Numeric,
     }
 
-    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
     pub struct PermissionV {
+        /// Allowances granted to every holder, where `This` stands for the holder
         pub permission: super::allow::AllowSet,
     }
 
+    /// A grant carries nothing besides its existence.
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct AccountPermissionV;
+
+    /// A compiled component, addressed by the hash of its bytes.
     #[derive(Debug, Clone)]
     pub struct ExecutableV {
+        pub hash: Hash,
         #[debug("Wasm Component")]
         pub component: wasmtime::component::Component,
+        /// The bytes the component was compiled from, kept for persisting it
+        #[debug(skip)]
+        pub bytes: std::sync::Arc<[u8]>,
+    }
+
+    impl ExecutableV {
+        /// Compiles the component bytes on the engine.
+        ///
+        /// The engine must be configured with `consume_fuel(true)`, so that every run of the
+        /// component can be held to its fuel limit.
+        pub fn new(engine: &wasmtime::Engine, bytes: &[u8]) -> wasmtime::Result<Self> {
+            use sha2::Digest;
+
+            // Fuel can only be read from a store of an engine that consumes it
+            if wasmtime::Store::new(engine, ()).get_fuel().is_err() {
+                return Err(wasmtime::Error::msg(
+                    "engine should be configured with `consume_fuel(true)`",
+                ));
+            }
+            let hash = sha2::Sha256::digest(bytes).into();
+            let component = wasmtime::component::Component::new(engine, bytes)?;
+
+            Ok(Self {
+                hash,
+                component,
+                bytes: bytes.into(),
+            })
+        }
     }
 
+    /// Components built from the same bytes are interchangeable.
     impl PartialEq for ExecutableV {
-        fn eq(&self, _other: &Self) -> bool {
-            unimplemented!("PoC does not support equality for ExecutableV")
+        fn eq(&self, other: &Self) -> bool {
+            self.hash == other.hash
         }
     }
 
     impl Eq for ExecutableV {}
+
+    /// Serializes as the hash alone, the same as in [`Archive`], since the component cannot be.
+    impl Serialize for ExecutableV {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.hash.serialize(serializer)
+        }
+    }
+
+    /// Views as archived, where executables are known by their hash alone.
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct Archive;
+
+    impl Mode for Archive {
+        type Account = AccountV;
+        type AssetDefinition = AssetDefinitionV;
+        type AccountAsset = AccountAssetV;
+        type Permission = PermissionV;
+        type AccountPermission = AccountPermissionV;
+        type Executable = Hash;
+    }
+
+    impl From<NodeValue<View>> for NodeValue<Archive> {
+        fn from(value: NodeValue<View>) -> Self {
+            match value {
+                NodeValue::Account(value) => NodeValue::Account(value),
+                NodeValue::AssetDefinition(value) => NodeValue::AssetDefinition(value),
+                NodeValue::AccountAsset(value) => NodeValue::AccountAsset(value),
+                NodeValue::Permission(value) => NodeValue::Permission(value),
+                NodeValue::AccountPermission(value) => NodeValue::AccountPermission(value),
+                NodeValue::Executable(value) => NodeValue::Executable(value.hash),
+            }
+        }
+    }
+
+    impl From<ViewSet> for Tree<Archive> {
+        fn from(view: ViewSet) -> Self {
+            Tree(view.0.into_iter().map(|(k, v)| (k, v.into())).collect())
+        }
+    }
 }
 
 pub mod write {
     use super::general::*;
+    use serde::{Deserialize, Serialize};
 
-    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
     pub struct Write;
 
     impl Mode for Write {
+        type Account = AccountW;
+        type AssetDefinition = AssetDefinitionW;
         type AccountAsset = AccountAssetW;
+        type Permission = PermissionW;
+        type AccountPermission = AccountPermissionW;
+        type Executable = ExecutableW;
     }
 
     pub type WriteSet = FlexTree<Write>;
 
-    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum AccountW {
+        Register,
+        Unregister,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum AssetDefinitionW {
+        /// Defines the asset with the given decimal scale
+        Register {
+            scale: u8,
+        },
+        Unregister,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
     pub enum AccountAssetW {
-        Send(u32),
-        Receive(u32),
+        Send(Numeric),
+        Receive(Numeric),
+        Mint(Numeric),
+        Burn(Numeric),
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum PermissionW {
+        /// Defines the permission with the allowances it grants
+        Define(super::view::PermissionV),
+        /// Replaces the allowances of a defined permission, even while it is granted
+        Redefine(super::view::PermissionV),
+        Delete,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum AccountPermissionW {
+        Grant,
+        Revoke,
+    }
+
+    /// Carries the bytes of a component implementing the `instruction` world, or the `authorizer`
+    /// world under the reserved [`AUTHORIZER`](crate::state::AUTHORIZER) key.
+    #[derive(derive_more::Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum ExecutableW {
+        #[debug("Register({} bytes)", _0.len())]
+        Register(Vec<u8>),
+        #[debug("Upgrade({} bytes)", _0.len())]
+        Upgrade(Vec<u8>),
+        Unregister,
     }
 }
 
 pub mod event {
-    use super::{general::*, write::AccountAssetW, write::WriteSet};
+    use super::general::*;
+    use super::write::{
+        AccountAssetW, AccountPermissionW, AccountW, AssetDefinitionW, ExecutableW, PermissionW,
+        WriteSet,
+    };
+    use serde::{Deserialize, Serialize};
 
-    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
     pub struct Event;
 
     impl Mode for Event {
+        type Account = AccountE;
+        type AssetDefinition = AssetDefinitionE;
         type AccountAsset = AccountAssetE;
+        type Permission = PermissionE;
+        type AccountPermission = AccountPermissionE;
+        type Executable = ExecutableE;
     }
 
     pub type EventSet = Tree<Event>;
 
-    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    #[repr(u8)]
+    pub enum AccountE {
+        Read = 0b0000_0001,
+        Register = 0b0000_0010,
+        Unregister = 0b0000_0100,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    #[repr(u8)]
+    pub enum AssetDefinitionE {
+        Read = 0b0000_0001,
+        Register = 0b0000_0010,
+        Unregister = 0b0000_0100,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
     #[repr(u8)]
     pub enum AccountAssetE {
         Read = 0b0000_0001,
@@ -300,6 +703,32 @@ pub mod event {
         Burn = 0b0010_0000,
     }
 
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    #[repr(u8)]
+    pub enum PermissionE {
+        Read = 0b0000_0001,
+        Define = 0b0000_0010,
+        Delete = 0b0000_0100,
+        Redefine = 0b0000_1000,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    #[repr(u8)]
+    pub enum AccountPermissionE {
+        Read = 0b0000_0001,
+        Grant = 0b0000_0010,
+        Revoke = 0b0000_0100,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    #[repr(u8)]
+    pub enum ExecutableE {
+        Read = 0b0000_0001,
+        Register = 0b0000_0010,
+        Unregister = 0b0000_0100,
+        Upgrade = 0b0000_1000,
+    }
+
     impl From<(WriteSet, AccountK)> for EventSet {
         fn from((write_set, authority): (WriteSet, AccountK)) -> Self {
             Tree(
@@ -308,12 +737,54 @@ pub mod event {
                     .into_iter()
                     .map(|(k, v)| {
                         let value = match v {
+                            NodeValue::Account(AccountW::Register) => {
+                                NodeValue::Account(AccountE::Register)
+                            }
+                            NodeValue::Account(AccountW::Unregister) => {
+                                NodeValue::Account(AccountE::Unregister)
+                            }
+                            NodeValue::AssetDefinition(AssetDefinitionW::Register { .. }) => {
+                                NodeValue::AssetDefinition(AssetDefinitionE::Register)
+                            }
+                            NodeValue::AssetDefinition(AssetDefinitionW::Unregister) => {
+                                NodeValue::AssetDefinition(AssetDefinitionE::Unregister)
+                            }
                             NodeValue::AccountAsset(AccountAssetW::Send(_)) => {
                                 NodeValue::AccountAsset(AccountAssetE::Send)
                             }
                             NodeValue::AccountAsset(AccountAssetW::Receive(_)) => {
                                 NodeValue::AccountAsset(AccountAssetE::Receive)
                             }
+                            NodeValue::AccountAsset(AccountAssetW::Mint(_)) => {
+                                NodeValue::AccountAsset(AccountAssetE::Mint)
+                            }
+                            NodeValue::AccountAsset(AccountAssetW::Burn(_)) => {
+                                NodeValue::AccountAsset(AccountAssetE::Burn)
+                            }
+                            NodeValue::Permission(PermissionW::Define(_)) => {
+                                NodeValue::Permission(PermissionE::Define)
+                            }
+                            NodeValue::Permission(PermissionW::Redefine(_)) => {
+                                NodeValue::Permission(PermissionE::Redefine)
+                            }
+                            NodeValue::Permission(PermissionW::Delete) => {
+                                NodeValue::Permission(PermissionE::Delete)
+                            }
+                            NodeValue::AccountPermission(AccountPermissionW::Grant) => {
+                                NodeValue::AccountPermission(AccountPermissionE::Grant)
+                            }
+                            NodeValue::AccountPermission(AccountPermissionW::Revoke) => {
+                                NodeValue::AccountPermission(AccountPermissionE::Revoke)
+                            }
+                            NodeValue::Executable(ExecutableW::Register(_)) => {
+                                NodeValue::Executable(ExecutableE::Register)
+                            }
+                            NodeValue::Executable(ExecutableW::Upgrade(_)) => {
+                                NodeValue::Executable(ExecutableE::Upgrade)
+                            }
+                            NodeValue::Executable(ExecutableW::Unregister) => {
+                                NodeValue::Executable(ExecutableE::Unregister)
+                            }
                         };
                         (k.resolve(authority.0.clone()), value)
                     })
@@ -324,21 +795,148 @@ pub mod event {
 }
 
 pub mod allow {
+    use super::event::*;
     use super::general::*;
+    use serde::{Deserialize, Serialize};
 
-    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
     pub struct Allow;
 
     impl Mode for Allow {
+        type Account = AccountA;
+        type AssetDefinition = AssetDefinitionA;
         type AccountAsset = AccountAssetA;
+        type Permission = PermissionA;
+        type AccountPermission = AccountPermissionA;
+        type Executable = ExecutableA;
     }
 
     pub type AllowSet = FlexFuzzyTree<Allow>;
 
-    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct AccountA {
+        pub bit_mask: u8,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct AssetDefinitionA {
+        pub bit_mask: u8,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
     pub struct AccountAssetA {
         pub bit_mask: u8,
     }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct PermissionA {
+        pub bit_mask: u8,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct AccountPermissionA {
+        pub bit_mask: u8,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct ExecutableA {
+        pub bit_mask: u8,
+    }
+
+    /// Bits of the allowance that stand for no event of its node type, or `None` if the allowance is
+    /// for another node type than its key.
+    pub fn undefined_bits(key: &FlexFuzzyNodeKey, value: &NodeValue<Allow>) -> Option<u8> {
+        let (bit_mask, defined) = match (key, value) {
+            (FlexFuzzyNodeKey::Account(_), NodeValue::Account(AccountA { bit_mask })) => (
+                bit_mask,
+                AccountE::Read as u8 | AccountE::Register as u8 | AccountE::Unregister as u8,
+            ),
+            (
+                FlexFuzzyNodeKey::AssetDefinition(_),
+                NodeValue::AssetDefinition(AssetDefinitionA { bit_mask }),
+            ) => (
+                bit_mask,
+                AssetDefinitionE::Read as u8
+                    | AssetDefinitionE::Register as u8
+                    | AssetDefinitionE::Unregister as u8,
+            ),
+            (
+                FlexFuzzyNodeKey::AccountAsset(_),
+                NodeValue::AccountAsset(AccountAssetA { bit_mask }),
+            ) => (
+                bit_mask,
+                AccountAssetE::Read as u8
+                    | AccountAssetE::Receive as u8
+                    | AccountAssetE::Send as u8
+                    | AccountAssetE::Mint as u8
+                    | AccountAssetE::Burn as u8,
+            ),
+            (FlexFuzzyNodeKey::Permission(_), NodeValue::Permission(PermissionA { bit_mask })) => (
+                bit_mask,
+                PermissionE::Read as u8
+                    | PermissionE::Define as u8
+                    | PermissionE::Delete as u8
+                    | PermissionE::Redefine as u8,
+            ),
+            (
+                FlexFuzzyNodeKey::AccountPermission(_),
+                NodeValue::AccountPermission(AccountPermissionA { bit_mask }),
+            ) => (
+                bit_mask,
+                AccountPermissionE::Read as u8
+                    | AccountPermissionE::Grant as u8
+                    | AccountPermissionE::Revoke as u8,
+            ),
+            (FlexFuzzyNodeKey::Executable(_), NodeValue::Executable(ExecutableA { bit_mask })) => (
+                bit_mask,
+                ExecutableE::Read as u8
+                    | ExecutableE::Register as u8
+                    | ExecutableE::Unregister as u8
+                    | ExecutableE::Upgrade as u8,
+            ),
+            _ => return None,
+        };
+
+        Some(bit_mask & !defined)
+    }
+}
+
+pub mod verdict {
+    use super::event::Event;
+    use super::general::*;
+    use super::read::Read;
+    use serde::{Deserialize, Serialize};
+
+    /// A signal the authorizer rejected, with the bits it required and the bits granted for it.
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(bound(
+        serialize = "K: Serialize, NodeValue<T>: Serialize",
+        deserialize = "K: Deserialize<'de>, NodeValue<T>: Deserialize<'de>"
+    ))]
+    pub struct Rejection<K, T: Mode> {
+        pub key: K,
+        pub value: NodeValue<T>,
+        pub required: u8,
+        pub granted: u8,
+    }
+
+    pub type ReadRejection = Rejection<FuzzyNodeKey, Read>;
+
+    pub type WriteRejection = Rejection<NodeKey, Event>;
+
+    /// How the authorizer judged a read request.
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct ReadVerdict {
+        /// The parts of the request that may be read
+        pub approved: super::read::ReadSet,
+        pub rejected: Vec<ReadRejection>,
+    }
+
+    /// How the authorizer judged a write request, which it approves as a whole or not at all.
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct WriteVerdict {
+        pub rejected: Vec<WriteRejection>,
+    }
 }
 
 #[cfg(test)]
@@ -396,4 +994,23 @@ mod tests {
             general::CompositeKey("alice".into(), "rose".into())
         );
     }
+
+    #[test]
+    fn numeric_aligns_scales() {
+        let one = general::Numeric::new(1, 0);
+        let cent = general::Numeric::new(1, 2);
+
+        assert_eq!(one.checked_add(cent), Some(general::Numeric::new(101, 2)));
+        assert_eq!(one.checked_sub(cent), Some(general::Numeric::new(99, 2)));
+        assert_eq!(cent.checked_sub(one), None);
+        assert_eq!(cent.rescale(0), None);
+        assert_eq!(
+            general::Numeric::new(100, 2).rescale(0),
+            Some(general::Numeric::new(1, 0))
+        );
+        assert_eq!(general::Numeric::new(u128::MAX, 0).rescale(1), None);
+        assert_eq!(general::Numeric::new(u128::MAX, 0).checked_add(one), None);
+        assert_eq!(general::Numeric::new(5, 3).to_string(), "0.005");
+        assert_eq!(general::Numeric::new(1234, 2).to_string(), "12.34");
+    }
 }